@@ -0,0 +1,117 @@
+use crate::screen::Screen;
+use crate::theme::ColourDepth;
+use crate::terminal::{Terminal, Size, Position};
+use crossterm::event::{poll, read, Event};
+use std::{io::Error, panic::{set_hook, take_hook}, time::Duration};
+
+// Everything the editor needs from the terminal, so it can also run against an in-memory screen
+pub trait Backend {
+
+    fn initialize(&mut self) -> Result<(), Error>;
+
+    fn terminate(&mut self) -> Result<(), Error>;
+
+    fn size(&self) -> Result<Size, Error>;
+
+    fn colour_depth(&self) -> ColourDepth;
+
+    fn set_title(&mut self, title: &str) -> Result<(), Error>;
+
+    fn clear_screen(&mut self) -> Result<(), Error>;
+
+    fn render(&mut self, previous: &Screen, current: &Screen) -> Result<(), Error>;
+
+    fn move_cursor_to(&mut self, position: Position) -> Result<(), Error>;
+
+    fn show_cursor(&mut self) -> Result<(), Error>;
+
+    fn hide_cursor(&mut self) -> Result<(), Error>;
+
+    fn print(&mut self, string: &str) -> Result<(), Error>;
+
+    fn execute(&mut self) -> Result<(), Error>;
+
+}
+
+// Yields `None` once there are no more events to process
+pub trait EventSource {
+
+    // Waits up to `timeout` for an event, returning whether one is ready
+    fn poll(&mut self, timeout: Duration) -> Result<bool, Error>;
+
+    fn read_event(&mut self) -> Result<Option<Event>, Error>;
+
+}
+
+pub struct CrosstermBackend;
+
+impl Backend for CrosstermBackend {
+
+    fn initialize(&mut self) -> Result<(), Error> {
+        let current_hook = take_hook();
+        set_hook(Box::new(move |panic_info| {
+            let _ = Terminal::terminate();
+            current_hook(panic_info);
+        }));
+        Terminal::initialize()
+    }
+
+    fn terminate(&mut self) -> Result<(), Error> {
+        Terminal::terminate()
+    }
+
+    fn size(&self) -> Result<Size, Error> {
+        Terminal::size()
+    }
+
+    fn colour_depth(&self) -> ColourDepth {
+        ColourDepth::detect()
+    }
+
+    fn set_title(&mut self, title: &str) -> Result<(), Error> {
+        Terminal::set_title(title)
+    }
+
+    fn clear_screen(&mut self) -> Result<(), Error> {
+        Terminal::clear_screen()
+    }
+
+    fn render(&mut self, previous: &Screen, current: &Screen) -> Result<(), Error> {
+        Terminal::render(previous, current)
+    }
+
+    fn move_cursor_to(&mut self, position: Position) -> Result<(), Error> {
+        Terminal::move_cursor_to(position)
+    }
+
+    fn show_cursor(&mut self) -> Result<(), Error> {
+        Terminal::show_cursor()
+    }
+
+    fn hide_cursor(&mut self) -> Result<(), Error> {
+        Terminal::hide_cursor()
+    }
+
+    fn print(&mut self, string: &str) -> Result<(), Error> {
+        Terminal::print(string)
+    }
+
+    fn execute(&mut self) -> Result<(), Error> {
+        Terminal::execute()
+    }
+
+}
+
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+
+    fn poll(&mut self, timeout: Duration) -> Result<bool, Error> {
+        poll(timeout)
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, Error> {
+        read().map(Some)
+    }
+
+}
\ No newline at end of file
@@ -1,34 +1,86 @@
-use std::io::{Error, Write};
-use std::fs::File;
+use std::{io::Error, ops::Range};
+use unicode_segmentation::UnicodeSegmentation;
 use crate::editor::Location;
+use crate::encoding::Encoding;
+use crate::history::{Edit, History};
 use crate::line::Line;
 
+const BRACKETS: [(&str, &str); 3] = [("(", ")"), ("[", "]"), ("{", "}")];
+
+// Bracket matching gives up after this many lines so that huge blocks do not slow down every redraw
+const MAX_BRACKET_LINES: usize = 10_000;
+
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+
+    // Files keep whichever ending their first line uses
+    fn detect(text: &str) -> Self {
+        match text.find('\n') {
+            Some(index) if text[..index].ends_with('\r') => Self::Crlf,
+            _ => Self::Lf,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+
+}
+
 #[derive(Default)]
 pub struct Buffer {
     pub save_file_path: Option<String>,
     lines: Vec<Line>,
     pub is_modified: bool,
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+    history: History,
 }
 
 impl Buffer {
 
-    pub fn load(file_path: &str) -> Result<Buffer, Error> {
-        let file_contents = std::fs::read_to_string(file_path)?;
-        let mut lines: Vec<Line> = Vec::new();
-        for line in file_contents.lines() {
-            lines.push(Line::from(line));
-        }
-        Ok(Self{save_file_path: Some(file_path.to_string()), lines, is_modified: false})
+    pub fn load(file_path: &str, encoding: Encoding) -> Result<Buffer, Error> {
+        let file_contents = encoding.decode(std::fs::read(file_path)?)?;
+        let mut buffer = Self::from_text(&file_contents, encoding);
+        buffer.save_file_path = Some(file_path.to_string());
+        Ok(buffer)
+    }
+
+    pub fn from_text(text: &str, encoding: Encoding) -> Buffer {
+        let lines = text.lines().map(Line::from).collect();
+        Self{save_file_path: None, lines, is_modified: false, encoding, line_ending: LineEnding::detect(text), history: History::default()}
     }
 
     pub fn save_file(&self) -> Result<(), Error> {
-        if let Some(file_name) = &self.save_file_path {
-            let mut file = File::create(file_name)?;
-            for line in &self.lines {
-                writeln!(file, "{line}")?;
-            }
+        match &self.save_file_path {
+            Some(file_name) => self.save_as(file_name),
+            None => Ok(()),
         }
-        Ok(())
+    }
+
+    pub fn save_as(&self, file_name: &str) -> Result<(), Error> {
+        let mut contents = String::new();
+        for line in &self.lines {
+            contents.push_str(&line.string);
+            contents.push_str(self.line_ending.as_str());
+        }
+        std::fs::write(file_name, self.encoding.encode(&contents)?)
     }
 
     pub fn is_empty(&self) -> bool {
@@ -52,49 +104,340 @@ impl Buffer {
         }
     }
 
-    pub fn insert_character(&mut self, location: Location, character: char) {
+    pub fn get_text(&self, start: Location, end: Location) -> String {
+        let (start, end) = (self.clamp(start), self.clamp(end));
+        let mut text = String::new();
+        for y in start.y..=end.y {
+            let Some(line) = self.lines.get(y) else {
+                break;
+            };
+            let from = if y == start.y { start.x } else { 0 };
+            let to = if y == end.y { end.x } else { line.len() };
+            text.push_str(&line.get(from..to));
+            if y < end.y {
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    pub fn insert(&mut self, location: Location, text: &str) -> Location {
+        let new_line = location.y == self.lines.len();
+        if location.y > self.lines.len() {
+            return location;
+        }
+        let end = self.apply_insert(location, text);
+        self.history.record(Edit::Insert{start: location, end, text: text.to_string(), new_line});
+        end
+    }
+
+    fn apply_insert(&mut self, location: Location, text: &str) -> Location {
         let Location{x, y} = location;
-        if y as usize == self.lines.len() {
-            self.lines.push(Line{string: character.to_string()});
+        if y == self.lines.len() {
+            self.lines.push(Line::default());
         }
-        else if let Some(line) = self.lines.get_mut(y as usize){
-            line.string = format!("{}{}{}", line.get(0..x as usize), character, line.get(x as usize..line.len()));
+        let Some(line) = self.lines.get(y) else {
+            return location;
+        };
+        let prefix = line.get(0..x);
+        let suffix = line.get(x..line.len());
+        let mut segments: Vec<&str> = text.split('\n').collect();
+        let last = segments.pop().unwrap_or_default();
+        let end = if segments.is_empty() {
+            self.lines[y] = Line::from(&format!("{prefix}{last}{suffix}"));
+            Location{x: x + Line::from(last).len(), y}
+        }
+        else {
+            self.lines[y] = Line::from(&format!("{prefix}{}", segments[0]));
+            let new_lines = segments[1..].iter()
+                .map(|segment| Line::from(segment))
+                .chain(std::iter::once(Line::from(&format!("{last}{suffix}"))));
+            let next = y + 1;
+            self.lines.splice(next..next, new_lines);
+            Location{x: Line::from(last).len(), y: y + segments.len()}
+        };
+        self.is_modified = true;
+        end
+    }
+
+    pub fn delete(&mut self, start: Location, end: Location) -> String {
+        let (start, end) = (self.clamp(start), self.clamp(end));
+        let removed = self.apply_delete(start, end);
+        if !removed.is_empty() {
+            self.history.record(Edit::Delete{start, end, text: removed.clone()});
         }
-        else{
-            panic!("Error: Could not add character.");
+        removed
+    }
+
+    fn apply_delete(&mut self, start: Location, end: Location) -> String {
+        if self.lines.is_empty() || start >= end {
+            return String::new();
         }
+        let removed = self.get_text(start, end);
+        let prefix = self.lines[start.y].get(0..start.x);
+        let suffix = self.lines[end.y].get(end.x..self.lines[end.y].len());
+        self.lines[start.y] = Line::from(&format!("{prefix}{suffix}"));
+        self.lines.drain(start.y + 1..=end.y);
         self.is_modified = true;
+        removed
+    }
+
+    pub fn insert_character(&mut self, location: Location, character: char) {
+        self.insert(location, &character.to_string());
     }
 
     pub fn delete_character(&mut self, location: Location) {
         let Location{x, y} = location;
-        if let Some(line) = self.lines.get(y as usize) {
-            if (x as usize) == line.len() {
-                self.lines[y as usize].string = format!("{}{}", line.get(0..x as usize), self.lines.remove((y + 1) as usize).string);
+        let end = if x < self.get_num_columns(y) {
+            Location{x: x + 1, y}
+        }
+        else {
+            Location{x: 0, y: y + 1}
+        };
+        self.delete(location, end);
+    }
+
+    // On the line past the end this only makes that line real, as there is nothing to split
+    pub fn enter(&mut self, location: Location) {
+        let text = if location.y == self.get_num_rows() { "" } else { "\n" };
+        self.insert(location, text);
+    }
+
+    pub fn begin_change(&mut self, cursor: Location, merge: bool) {
+        self.history.begin(cursor, merge);
+    }
+
+    pub fn end_change(&mut self, cursor: Location) {
+        self.history.end(cursor);
+    }
+
+    pub fn edit_count(&self) -> usize {
+        self.history.edit_count()
+    }
+
+    pub fn edits_since(&self, count: usize) -> Vec<Edit> {
+        self.history.edits_since(count)
+    }
+
+    // Returns where the cursor was before the change that was undone
+    pub fn undo(&mut self) -> Option<Location> {
+        let change = self.history.undo()?;
+        for edit in change.edits.iter().rev() {
+            match edit {
+                Edit::Insert{start, end, new_line, ..} => {
+                    self.apply_delete(*start, *end);
+                    if *new_line {
+                        self.lines.pop();
+                    }
+                },
+                Edit::Delete{start, text, ..} => {
+                    self.apply_insert(*start, text);
+                },
             }
-            else {
-                self.lines[y as usize].string = format!("{}{}", line.get(0..x as usize), line.get((x+1) as usize..line.len()));
+        }
+        self.is_modified = !self.history.is_saved();
+        Some(change.before)
+    }
+
+    pub fn redo(&mut self) -> Option<Location> {
+        let change = self.history.redo()?;
+        for edit in &change.edits {
+            match edit {
+                Edit::Insert{start, text, ..} => {
+                    self.apply_insert(*start, text);
+                },
+                Edit::Delete{start, end, ..} => {
+                    self.apply_delete(*start, *end);
+                },
             }
         }
+        self.is_modified = !self.history.is_saved();
+        Some(change.after)
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.history.mark_saved();
+        self.is_modified = false;
+    }
+
+    // Whole lines as text, each followed by a line break
+    pub fn get_lines(&self, lines: Range<usize>) -> String {
+        let mut text = String::new();
+        for line in self.lines.get(lines).unwrap_or_default() {
+            text.push_str(&line.string);
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn delete_lines(&mut self, lines: Range<usize>) -> String {
+        let text = self.get_lines(lines.clone());
+        if lines.is_empty() || lines.start >= self.lines.len() {
+            return text;
+        }
+        let last = self.lines.len() - 1;
+        if lines.end <= last {
+            self.delete(Location{x: 0, y: lines.start}, Location{x: 0, y: lines.end});
+        }
+        else if lines.start > 0 {
+            self.delete(self.end_of_line(Location{x: 0, y: lines.start - 1}), self.end_of_line(Location{x: 0, y: last}));
+        }
         else {
-            panic!("Error: Could not delete character.");
+            self.delete(Location::default(), self.end_of_line(Location{x: 0, y: last}));
         }
-        self.is_modified = true;
+        text
     }
 
-    pub fn enter(&mut self, location: Location) {
-        let Location{x, y} = location;
-        if let Some(line) = self.lines.get(y as usize) {
-            let current_line: Line = Line{string: line.get(0..(x as usize))};
-            let next_line: Line = Line{string: line.get((x as usize)..line.len())};
+    // Inserts text ending in a line break so that it starts line `y`
+    pub fn insert_lines(&mut self, y: usize, text: &str) {
+        if y < self.lines.len() {
+            self.insert(Location{x: 0, y}, text);
+        }
+        else if let Some(last) = self.lines.len().checked_sub(1) {
+            let text = text.strip_suffix('\n').unwrap_or(text);
+            self.insert(self.end_of_line(Location{x: 0, y: last}), &format!("\n{text}"));
+        }
+        else {
+            self.insert(Location::default(), text.strip_suffix('\n').unwrap_or(text));
+        }
+    }
 
-            self.lines[y as usize].string = current_line.string;
-            self.lines.insert((y + 1) as usize, next_line);
+    pub fn find(&self, needle: &str, from: Location) -> Option<Location> {
+        if needle.is_empty() {
+            return None;
         }
-        else if (y as usize) == self.get_num_rows() {
-            self.lines.push(Line{string: String::from("")});
+        self.lines
+            .iter()
+            .enumerate()
+            .skip(from.y)
+            .find_map(|(y, line)| line.find(needle, if y == from.y { from.x } else { 0 }).map(|x| Location{x, y}))
+    }
+
+    // Scans line by line from the bracket at `location`, counting nested pairs of the same kind
+    pub fn matching_bracket(&self, location: Location) -> Option<Location> {
+        let bracket = self.lines.get(location.y)?.get(location.x..location.x + 1);
+        let (open, close) = *BRACKETS.iter().find(|(open, close)| bracket == *open || bracket == *close)?;
+        let forward = bracket == open;
+        let mut depth = 0_usize;
+        let mut visit = |grapheme: &str| {
+            if grapheme == open && forward || grapheme == close && !forward {
+                depth += 1;
+            }
+            else if grapheme == close && forward || grapheme == open && !forward {
+                depth -= 1;
+            }
+            depth == 0
+        };
+        if forward {
+            for (y, line) in self.lines.iter().enumerate().skip(location.y).take(MAX_BRACKET_LINES) {
+                let skip = if y == location.y { location.x } else { 0 };
+                if let Some(x) = line.string.graphemes(true).enumerate().skip(skip).find_map(|(x, grapheme)| visit(grapheme).then_some(x)) {
+                    return Some(Location{x, y});
+                }
+            }
         }
-        self.is_modified = true;
+        else {
+            for y in (location.y.saturating_sub(MAX_BRACKET_LINES - 1)..=location.y).rev() {
+                let line = &self.lines[y];
+                let len = line.len();
+                let skip = if y == location.y { len.saturating_sub(location.x + 1) } else { 0 };
+                if let Some(x) = line.string.graphemes(true).rev().enumerate().skip(skip).find_map(|(offset, grapheme)| visit(grapheme).then_some(len - 1 - offset)) {
+                    return Some(Location{x, y});
+                }
+            }
+        }
+        None
+    }
+
+    pub fn end_of_line(&self, location: Location) -> Location {
+        Location{x: self.get_num_columns(location.y), y: location.y}
+    }
+
+    fn clamp(&self, location: Location) -> Location {
+        let Some(last) = self.lines.len().checked_sub(1) else {
+            return Location::default();
+        };
+        if location.y > last {
+            Location{x: self.get_num_columns(last), y: last}
+        }
+        else {
+            Location{x: location.x.min(self.get_num_columns(location.y)), y: location.y}
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Buffer, LineEnding};
+    use crate::editor::Location;
+    use crate::encoding::Encoding;
+    use crate::testing::temp_dir;
+    use std::fs;
+
+    #[test]
+    fn insert_and_delete_span_lines() {
+        let mut buffer = Buffer::from_text("one\ntwo\n", Encoding::Utf8);
+        let end = buffer.insert(Location{x: 1, y: 0}, "X\nY");
+        assert_eq!(end, Location{x: 1, y: 1});
+        assert_eq!(buffer.get_text(Location::default(), Location{x: 3, y: 2}), "oX\nYne\ntwo");
+        assert_eq!(buffer.delete(Location{x: 2, y: 0}, Location{x: 1, y: 1}), "\nY");
+        assert_eq!(buffer.get_num_rows(), 2);
+    }
+
+    #[test]
+    fn crlf_files_are_saved_with_crlf() {
+        let path = temp_dir("crlf_files").join("dos.txt");
+        fs::write(&path, "a\r\nb\r\n").unwrap();
+        let mut buffer = Buffer::load(path.to_str().unwrap(), Encoding::Utf8).unwrap();
+        assert!(buffer.line_ending == LineEnding::Crlf);
+        buffer.insert(Location{x: 1, y: 1}, "c");
+        buffer.save_file().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\r\nbc\r\n");
+    }
+
+    #[test]
+    fn undo_and_redo_restore_the_text_and_saved_state() {
+        let mut buffer = Buffer::from_text("one\n", Encoding::Utf8);
+        buffer.begin_change(Location::default(), false);
+        buffer.delete(Location::default(), Location{x: 3, y: 0});
+        buffer.insert(Location::default(), "two\nthree");
+        buffer.end_change(Location{x: 5, y: 1});
+        buffer.insert(Location{x: 0, y: 2}, "four");
+        assert_eq!(buffer.get_num_rows(), 3);
+        assert_eq!(buffer.undo(), Some(Location{x: 0, y: 2}));
+        assert_eq!(buffer.get_num_rows(), 2);
+        assert_eq!(buffer.undo(), Some(Location::default()));
+        assert_eq!(buffer.get_text(Location::default(), Location{x: 3, y: 0}), "one");
+        assert!(!buffer.is_modified);
+        assert!(buffer.undo().is_none());
+        assert_eq!(buffer.redo(), Some(Location{x: 5, y: 1}));
+        assert_eq!(buffer.get_text(Location::default(), Location{x: 5, y: 1}), "two\nthree");
+        assert!(buffer.is_modified);
+    }
+
+    #[test]
+    fn whole_lines_move_around_the_end_of_the_buffer() {
+        let mut buffer = Buffer::from_text("a\nb\nc", Encoding::Utf8);
+        assert_eq!(buffer.delete_lines(2..3), "c\n");
+        buffer.insert_lines(0, "c\n");
+        assert_eq!(buffer.get_lines(0..3), "c\na\nb\n");
+        assert_eq!(buffer.delete_lines(0..1), "c\n");
+        buffer.insert_lines(2, "c\n");
+        assert_eq!(buffer.get_lines(0..3), "a\nb\nc\n");
+        assert_eq!(buffer.get_num_rows(), 3);
+    }
+
+    #[test]
+    fn brackets_match_across_lines_and_nesting() {
+        let buffer = Buffer::from_text("fn f(a: [u8; 2]) {\n    g(a[0]);\n}", Encoding::Utf8);
+        assert_eq!(buffer.matching_bracket(Location{x: 17, y: 0}), Some(Location{x: 0, y: 2}));
+        assert_eq!(buffer.matching_bracket(Location{x: 0, y: 2}), Some(Location{x: 17, y: 0}));
+        assert_eq!(buffer.matching_bracket(Location{x: 4, y: 0}), Some(Location{x: 15, y: 0}));
+        assert_eq!(buffer.matching_bracket(Location{x: 10, y: 1}), Some(Location{x: 5, y: 1}));
+        assert!(buffer.matching_bracket(Location{x: 0, y: 0}).is_none());
+        assert!(Buffer::from_text("((x)", Encoding::Utf8).matching_bracket(Location::default()).is_none());
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,126 @@
+use crate::encoding::Encoding;
+use crate::goto::{GotoTarget, split_file_position};
+
+const NAME: &str = env!("CARGO_PKG_NAME");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub enum FileSource {
+    Path(String),
+    Stdin,
+}
+
+pub struct FileArgument {
+    pub source: FileSource,
+    pub target: Option<GotoTarget>,
+}
+
+#[derive(Default)]
+pub struct Arguments {
+    pub files: Vec<FileArgument>,
+    pub readonly: bool,
+    pub config_path: Option<String>,
+    pub encoding: Encoding,
+}
+
+pub enum Action {
+    Run(Arguments),
+    Help,
+    Version,
+}
+
+pub fn usage() -> String {
+    format!("Usage: {NAME} [OPTIONS] [+LINE] [FILE[:LINE[:COLUMN]]]...
+
+Arguments:
+  FILE              File to open, or - to read from stdin
+  +LINE             Open the following file at LINE
+
+Options:
+  -h, --help        Print this help and exit
+  -V, --version     Print the version and exit
+  -R, --readonly    Open files without allowing edits
+  --config PATH     Read settings from PATH
+  --encoding NAME   Read and write files as NAME (utf-8, latin-1)")
+}
+
+pub fn version() -> String {
+    format!("{NAME} {VERSION}")
+}
+
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Action, String> {
+    let mut arguments = Arguments::default();
+    let mut pending_target: Option<GotoTarget> = None;
+    let mut only_files = false;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if only_files {
+            arguments.files.push(file_argument(&arg, pending_target.take()));
+            continue;
+        }
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if arg.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Action::Help),
+            "-V" | "--version" => return Ok(Action::Version),
+            "-R" | "--readonly" => arguments.readonly = true,
+            "--config" => arguments.config_path = Some(value_for(&flag, inline_value, &mut args)?),
+            "--encoding" => {
+                let name = value_for(&flag, inline_value, &mut args)?;
+                arguments.encoding = Encoding::from_name(&name).ok_or_else(|| format!("Unsupported encoding: {name}"))?;
+            },
+            "--" => only_files = true,
+            "-" => arguments.files.push(FileArgument{source: FileSource::Stdin, target: pending_target.take()}),
+            _ if flag.starts_with('+') => {
+                let line = flag[1..].parse().map_err(|_| format!("Invalid line number: {flag}"))?;
+                pending_target = Some(GotoTarget::Line{line, column: None});
+            },
+            _ if flag.starts_with('-') => return Err(format!("Unknown option: {flag}")),
+            _ => arguments.files.push(file_argument(&arg, pending_target.take())),
+        }
+    }
+    if pending_target.is_some() {
+        return Err("+LINE must be followed by a file".to_string());
+    }
+    Ok(Action::Run(arguments))
+}
+
+fn value_for(flag: &str, inline_value: Option<String>, args: &mut impl Iterator<Item = String>) -> Result<String, String> {
+    inline_value.or_else(|| args.next()).ok_or_else(|| format!("{flag} requires a value"))
+}
+
+fn file_argument(arg: &str, pending_target: Option<GotoTarget>) -> FileArgument {
+    let (path, target) = split_file_position(arg);
+    FileArgument{source: FileSource::Path(path), target: pending_target.or(target)}
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{parse, Action, FileSource};
+
+    fn parse_args(args: &[&str]) -> Result<Action, String> {
+        parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn line_flag_applies_to_the_next_file() {
+        let Ok(Action::Run(arguments)) = parse_args(&["-R", "+12", "a.txt", "-", "--", "-b"]) else {
+            panic!("expected arguments");
+        };
+        assert!(arguments.readonly);
+        assert_eq!(arguments.files.len(), 3);
+        assert!(arguments.files[0].target.is_some());
+        assert!(matches!(arguments.files[1].source, FileSource::Stdin));
+        assert!(matches!(&arguments.files[2].source, FileSource::Path(path) if path == "-b"));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(parse_args(&["--frobnicate"]).err().unwrap(), "Unknown option: --frobnicate");
+        assert_eq!(parse_args(&["+3"]).err().unwrap(), "+LINE must be followed by a file");
+        assert!(parse_args(&["--encoding"]).is_err());
+    }
+
+}
\ No newline at end of file
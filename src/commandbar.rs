@@ -0,0 +1,83 @@
+use crate::editorcommand::{EditorCommand, Direction};
+use crate::screen::Screen;
+use crate::theme::{Face, Theme};
+use crate::terminal::{Size, Position};
+use crate::uicomponent::UIComponent;
+
+#[derive(Default)]
+pub struct CommandBar {
+    needs_redraw: bool,
+    prompt: String,
+    value: String,
+    cursor: usize,
+    size: Size,
+}
+
+impl CommandBar {
+
+    pub fn set_prompt(&mut self, prompt: &str) {
+        self.prompt = prompt.to_string();
+        self.value.clear();
+        self.cursor = 0;
+        self.set_redraw(true);
+    }
+
+    pub fn get_value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn handle_command(&mut self, command: &EditorCommand) {
+        match command {
+            EditorCommand::Insert(character) => {
+                self.value.insert(self.byte_offset(), *character);
+                self.cursor += 1;
+            },
+            EditorCommand::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.value.remove(self.byte_offset());
+            },
+            EditorCommand::Delete if self.cursor < self.value.chars().count() => {
+                self.value.remove(self.byte_offset());
+            },
+            EditorCommand::Move(Direction::Left) => self.cursor = self.cursor.saturating_sub(1),
+            EditorCommand::Move(Direction::Right) => self.cursor = (self.cursor + 1).min(self.value.chars().count()),
+            EditorCommand::Move(Direction::Home) => self.cursor = 0,
+            EditorCommand::Move(Direction::End) => self.cursor = self.value.chars().count(),
+            _ => return,
+        }
+        self.set_redraw(true);
+    }
+
+    pub fn get_cursor_column(&self) -> usize {
+        (self.prompt.chars().count() + self.cursor).min(self.size.num_columns.saturating_sub(1))
+    }
+
+    fn byte_offset(&self) -> usize {
+        self.value.char_indices().nth(self.cursor).map_or(self.value.len(), |(offset, _)| offset)
+    }
+
+}
+
+impl UIComponent for CommandBar {
+
+    fn set_redraw(&mut self, needs_redraw: bool) {
+        self.needs_redraw = needs_redraw;
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    fn draw(&mut self, screen: &mut Screen, theme: &Theme, start_row: usize) {
+        let line: String = format!("{}{}", self.prompt, self.value).chars().take(self.size.num_columns).collect();
+        screen.move_cursor_to(Position{row: start_row, column: 0});
+        screen.set_style(theme.style(Face::MessageBar));
+        screen.clear_line();
+        screen.print(&line);
+    }
+
+}
\ No newline at end of file
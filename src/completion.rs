@@ -0,0 +1,156 @@
+use crate::screen::Screen;
+use crate::theme::{Face, Theme};
+use crate::terminal::{Size, Position};
+use crate::uicomponent::UIComponent;
+use std::collections::HashMap;
+use unicode_width::UnicodeWidthStr;
+
+const MAX_ROWS: usize = 8;
+
+// Words gathered from the open buffers, with how often each occurs and how near it comes to the cursor
+#[derive(Default)]
+pub struct Candidates {
+    words: HashMap<String, (usize, usize)>,
+}
+
+impl Candidates {
+
+    // `distance` is in lines from the cursor, or `usize::MAX` for words in other buffers
+    pub fn add(&mut self, word: &str, distance: usize) {
+        match self.words.get_mut(word) {
+            Some((nearest, count)) => {
+                *nearest = (*nearest).min(distance);
+                *count += 1;
+            },
+            None => {
+                self.words.insert(word.to_string(), (distance, 1));
+            },
+        }
+    }
+
+    // Nearest first, then the most frequent, with ties in alphabetical order
+    pub fn ranked(self) -> Vec<String> {
+        let mut words: Vec<(String, (usize, usize))> = self.words.into_iter().collect();
+        words.sort_by(|(word, (distance, count)), (other, (other_distance, other_count))| {
+            distance.cmp(other_distance).then(other_count.cmp(count)).then(word.cmp(other))
+        });
+        words.into_iter().map(|(word, _)| word).collect()
+    }
+
+}
+
+// A list of completions drawn over the view, below the word being completed or above it when there is no room
+#[derive(Default)]
+pub struct Completion {
+    needs_redraw: bool,
+    // Every word collected for `prefix`, which `items` narrows down as more of the word is typed
+    words: Vec<String>,
+    prefix: String,
+    items: Vec<String>,
+    selected: usize,
+    anchor: Position,
+    size: Size,
+}
+
+impl Completion {
+
+    pub fn show(&mut self, words: Vec<String>, prefix: &str, anchor: Position) {
+        self.words = words;
+        self.prefix = prefix.to_string();
+        self.narrow(prefix, anchor);
+    }
+
+    // False when `prefix` no longer extends the one the words were collected for, so they have to be collected again
+    pub fn narrow(&mut self, prefix: &str, anchor: Position) -> bool {
+        if !prefix.starts_with(&self.prefix) {
+            return false;
+        }
+        self.items = self.words.iter().filter(|word| word.len() > prefix.len() && word.starts_with(prefix)).cloned().collect();
+        self.selected = 0;
+        self.anchor = anchor;
+        self.set_redraw(true);
+        true
+    }
+
+    pub fn hide(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn is_visible(&self) -> bool {
+        !self.items.is_empty()
+    }
+
+    pub fn select(&mut self, forward: bool) {
+        let count = self.items.len();
+        if count > 0 {
+            self.selected = if forward { (self.selected + 1) % count } else { (self.selected + count - 1) % count };
+            self.set_redraw(true);
+        }
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.items.get(self.selected).map(String::as_str)
+    }
+
+}
+
+impl UIComponent for Completion {
+
+    fn set_redraw(&mut self, needs_redraw: bool) {
+        self.needs_redraw = needs_redraw;
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    fn draw(&mut self, screen: &mut Screen, theme: &Theme, start_row: usize) {
+        let Size{num_rows, num_columns} = self.size;
+        let rows_below = num_rows.saturating_sub(self.anchor.row + 1);
+        let rows_above = self.anchor.row;
+        let (top, height) = if rows_below >= self.items.len().min(MAX_ROWS) || rows_below >= rows_above {
+            (self.anchor.row + 1, self.items.len().min(MAX_ROWS).min(rows_below))
+        }
+        else {
+            let height = self.items.len().min(MAX_ROWS).min(rows_above);
+            (self.anchor.row - height, height)
+        };
+        if height == 0 {
+            return;
+        }
+        let first = (self.selected + 1).saturating_sub(height);
+        let visible = &self.items[first..first + height];
+        let width = (visible.iter().map(|item| item.width()).max().unwrap_or(0) + 2).min(num_columns);
+        // Items are padded by a space, so the list starts a column early to line them up with the word
+        let column = self.anchor.column.saturating_sub(1).min(num_columns - width);
+        let text = theme.style(Face::Text);
+        for (offset, item) in visible.iter().enumerate() {
+            let face = if first + offset == self.selected { Face::CompletionSelected } else { Face::Completion };
+            let padding = width.saturating_sub(item.width() + 1);
+            screen.move_cursor_to(Position{row: start_row + top + offset, column});
+            screen.set_style(text.patch(theme.style(face)));
+            screen.print(&format!(" {item}{}", " ".repeat(padding)));
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Candidates;
+
+    #[test]
+    fn nearer_and_more_frequent_words_rank_first() {
+        let mut candidates = Candidates::default();
+        for (word, distance) in [("format", usize::MAX), ("for_each", 3), ("forward", 1), ("for_each", 5), ("fork", 3), ("format", usize::MAX)] {
+            candidates.add(word, distance);
+        }
+        assert_eq!(candidates.ranked(), vec!["forward", "for_each", "fork", "format"]);
+    }
+
+}
\ No newline at end of file
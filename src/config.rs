@@ -0,0 +1,241 @@
+use crate::keymap::Keymap;
+use crate::theme::Theme;
+use crate::statusbar::StatusFormat;
+use std::{env, fs, path::{Path, PathBuf}, time::Duration};
+
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub enum LineNumbers {
+    #[default]
+    Off,
+    Absolute,
+    Relative,
+    Hybrid,
+}
+
+impl LineNumbers {
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Self::Off),
+            "absolute" => Some(Self::Absolute),
+            "relative" => Some(Self::Relative),
+            "hybrid" => Some(Self::Hybrid),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Absolute,
+            Self::Absolute => Self::Relative,
+            Self::Relative => Self::Hybrid,
+            Self::Hybrid => Self::Off,
+        }
+    }
+
+}
+
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub enum WrapMode {
+    #[default]
+    Off,
+    Character,
+    Word,
+}
+
+impl WrapMode {
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Self::Off),
+            "character" => Some(Self::Character),
+            "word" => Some(Self::Word),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Word,
+            Self::Word => Self::Character,
+            Self::Character => Self::Off,
+        }
+    }
+
+}
+
+#[derive(Copy, Clone)]
+pub struct ViewSettings {
+    pub line_numbers: LineNumbers,
+    pub wrap: WrapMode,
+    pub tab_width: usize,
+    pub soft_tabs: bool,
+    pub show_whitespace: bool,
+    pub scroll_off: usize,
+    pub auto_pairs: bool,
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        Self{
+            line_numbers: LineNumbers::default(),
+            wrap: WrapMode::default(),
+            tab_width: 4,
+            soft_tabs: false,
+            show_whitespace: false,
+            scroll_off: 0,
+            auto_pairs: false,
+        }
+    }
+}
+
+pub struct Config {
+    pub keymap: Keymap,
+    pub view: ViewSettings,
+    pub theme: Theme,
+    pub status_format: StatusFormat,
+    pub message_timeout: Option<Duration>,
+    theme_name: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self{
+            keymap: Keymap::default(),
+            view: ViewSettings::default(),
+            theme: Theme::default(),
+            status_format: StatusFormat::default(),
+            message_timeout: Some(Duration::from_secs(5)),
+            theme_name: None,
+        }
+    }
+}
+
+impl Config {
+
+    pub fn load(path: Option<&str>) -> Result<Self, String> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+        let contents = fs::read_to_string(&path).map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+        let mut config = Self::parse(&contents).map_err(|err| format!("{}: {err}", path.display()))?;
+        if let Some(name) = &config.theme_name {
+            config.theme = Theme::load(&Self::theme_path(&path, name))?;
+        }
+        Ok(config)
+    }
+
+    fn default_path() -> Option<PathBuf> {
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_dir.join(env!("CARGO_PKG_NAME")).join("config"))
+    }
+
+    // A bare name refers to `themes/NAME.theme` next to the config file, anything else is a path
+    fn theme_path(config_path: &Path, name: &str) -> PathBuf {
+        let config_dir = config_path.parent().unwrap_or(Path::new("."));
+        if name.contains('/') || name.contains('.') {
+            config_dir.join(name)
+        }
+        else {
+            config_dir.join("themes").join(format!("{name}.theme"))
+        }
+    }
+
+    fn parse(contents: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        parse_settings(contents, |key, value| config.set(key, value))?;
+        Ok(config)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "keymap" => self.keymap = Keymap::from_name(value).ok_or_else(|| format!("unknown keymap `{value}`"))?,
+            "line_numbers" => {
+                self.view.line_numbers = LineNumbers::from_name(value).ok_or_else(|| format!("unknown line number mode `{value}`"))?;
+            },
+            "wrap" => self.view.wrap = WrapMode::from_name(value).ok_or_else(|| format!("unknown wrap mode `{value}`"))?,
+            "tab_width" => {
+                self.view.tab_width = value.parse().ok().filter(|width| *width > 0).ok_or_else(|| format!("invalid tab width `{value}`"))?;
+            },
+            "soft_tabs" => self.view.soft_tabs = parse_bool(value)?,
+            "show_whitespace" => self.view.show_whitespace = parse_bool(value)?,
+            "auto_pairs" => self.view.auto_pairs = parse_bool(value)?,
+            "scroll_off" => self.view.scroll_off = value.parse().map_err(|_| format!("invalid scroll off `{value}`"))?,
+            "status_format" => self.status_format = value.parse()?,
+            "message_timeout" => {
+                let seconds: u64 = value.parse().map_err(|_| format!("invalid message timeout `{value}`"))?;
+                self.message_timeout = (seconds > 0).then(|| Duration::from_secs(seconds));
+            },
+            "theme" => self.theme_name = (value != "default").then(|| value.to_string()),
+            _ => return Err(format!("unknown setting `{key}`")),
+        }
+        Ok(())
+    }
+
+}
+
+// One `key = value` setting per line, with a `#` at the start of a word beginning a comment
+pub fn parse_settings(contents: &str, mut set: impl FnMut(&str, &str) -> Result<(), String>) -> Result<(), String> {
+    for (index, line) in contents.lines().enumerate() {
+        let comment = line.char_indices().find(|(offset, character)| *character == '#' && line[..*offset].chars().next_back().is_none_or(char::is_whitespace));
+        let line = comment.map_or(line, |(offset, _)| &line[..offset]).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("line {}: expected `key = value`", index + 1));
+        };
+        set(key.trim(), value.trim()).map_err(|err| format!("line {}: {err}", index + 1))?;
+    }
+    Ok(())
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "on" | "yes" => Ok(true),
+        "false" | "off" | "no" => Ok(false),
+        _ => Err(format!("expected true or false, found `{value}`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Config, LineNumbers, WrapMode};
+    use crate::testing::temp_dir;
+    use crate::theme::Face;
+    use crossterm::style::Color;
+    use std::fs;
+
+    #[test]
+    fn parses_settings_and_comments() {
+        let config = Config::parse("# view\nline_numbers = hybrid\nwrap = word  # soft wrap\ntab_width = 8\nsoft_tabs = yes\n").unwrap();
+        assert!(config.view.line_numbers == LineNumbers::Hybrid);
+        assert!(config.view.wrap == WrapMode::Word);
+        assert_eq!(config.view.tab_width, 8);
+        assert!(config.view.soft_tabs);
+    }
+
+    #[test]
+    fn reports_the_offending_line() {
+        assert_eq!(Config::parse("wrap = word\ntab_width = 0").err().unwrap(), "line 2: invalid tab width `0`");
+        assert_eq!(Config::parse("colour").err().unwrap(), "line 1: expected `key = value`");
+    }
+
+    #[test]
+    fn loads_named_theme_next_to_the_config() {
+        let dir = temp_dir("loads_named_theme");
+        fs::create_dir(dir.join("themes")).unwrap();
+        fs::write(dir.join("themes").join("night.theme"), "text = fg=#c0c0c0 bg=#101010  # base colours\n").unwrap();
+        fs::write(dir.join("config"), "theme = night\n").unwrap();
+        let config = Config::load(dir.join("config").to_str()).unwrap();
+        assert_eq!(config.theme.style(Face::Text).background, Some(Color::Rgb{r: 16, g: 16, b: 16}));
+    }
+
+}
\ No newline at end of file
@@ -1,67 +1,220 @@
-use crate::editorcommand::EditorCommand;
-use crate::terminal::{Terminal, Size};
+use crate::editorcommand::{EditorCommand, Direction, MouseAction};
+use crate::backend::{Backend, EventSource, CrosstermBackend, TerminalEvents};
+use crate::terminal::{Size, Position};
 use crate::view::View;
 use crate::statusbar::StatusBar;
-use crate::messagebar::MessageBar;
+use crate::messagebar::{MessageBar, Severity};
+use crate::commandbar::CommandBar;
+use crate::completion::{Candidates, Completion};
+use crate::goto::GotoTarget;
 use crate::uicomponent::UIComponent;
+use crate::screen::Screen;
+use crate::theme::Theme;
+use crate::keymap::Keymap;
+use crate::killring::KillRing;
+use crate::cli::{Arguments, FileArgument, FileSource};
+use crate::config::Config;
+use crate::filetype::FileType;
+use crate::encoding::Encoding;
+use crate::buffer::LineEnding;
 
-use crossterm::event::{read, Event, KeyEvent, KeyEventKind};
-use std::{cmp, env, io::Error, panic::{set_hook, take_hook}};
+use crossterm::event::{Event, KeyEvent, KeyEventKind};
+use std::{cmp::Ordering, io::{stdin, Error, IsTerminal}, time::{Duration, Instant}};
 
-#[derive(Copy, Clone, Default)]
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub struct Location {
     pub x: usize,
     pub y: usize,
 }
 
+impl Ord for Location {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.y, self.x).cmp(&(other.y, other.x))
+    }
+}
+
+impl PartialOrd for Location {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub enum Mode {
+    #[default]
+    Insert,
+    Select,
+    Prompt,
+}
+
 #[derive(Default, Eq, PartialEq)]
 pub struct DocumentStatus {
     pub file_path: Option<String>,
     pub current_line: usize,
     pub total_lines: usize,
+    pub column: usize,
     pub is_modified: bool,
+    pub readonly: bool,
+    pub file_type: FileType,
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+    pub selection: Option<usize>,
+    pub mode: Mode,
+}
+
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+enum PromptType {
+    #[default]
+    None,
+    GotoLine,
+    SaveAs,
 }
 
-#[derive(Default)]
 pub struct Editor {
     should_quit: bool,
-    view: View,
+    views: Vec<View>,
+    current_view: usize,
     status_bar: StatusBar,
     message_bar: MessageBar,
+    command_bar: CommandBar,
+    completion: Completion,
+    kill_ring: KillRing,
+    prompt_type: PromptType,
     size: Size,
+    keymap: Keymap,
+    screen: Screen,
+    last_frame: Screen,
+    theme: Theme,
+    last_click: Option<(Instant, Position)>,
+    backend: Box<dyn Backend>,
+    events: Box<dyn EventSource>,
 }
 
 impl Editor {
 
-    pub fn new() -> Result<Self, Error> {
-        let current_hook = take_hook();
-        set_hook(Box::new(move |panic_info| {
-            let _ = Terminal::terminate();
-            current_hook(panic_info);
-        }));
-        Terminal::initialize()?;
-        let mut editor = Self::default();
-        editor.size = Terminal::size().unwrap_or_default();
-        editor.resize(editor.size);
+    pub fn new(mut arguments: Arguments, config: &Config) -> Result<Self, Error> {
+        if !stdin().is_terminal() && !arguments.files.iter().any(|file| matches!(file.source, FileSource::Stdin)) {
+            arguments.files.insert(0, FileArgument{source: FileSource::Stdin, target: None});
+        }
+        Self::with_backend(arguments, config, Box::new(CrosstermBackend), Box::new(TerminalEvents))
+    }
+
+    pub fn with_backend(arguments: Arguments, config: &Config, mut backend: Box<dyn Backend>, events: Box<dyn EventSource>) -> Result<Self, Error> {
+        let mut views = Vec::new();
+        let mut targets = Vec::new();
+        let mut errors = Vec::new();
+        for file in arguments.files {
+            let mut view = View::default();
+            view.set_settings(config.view);
+            let (name, result) = match &file.source {
+                FileSource::Path(file_path) => (file_path.as_str(), view.load(file_path, arguments.encoding)),
+                FileSource::Stdin => ("stdin", view.load_stdin(arguments.encoding)),
+            };
+            if let Err(err) = result {
+                errors.push(format!("Could not open {name}: {err}"));
+                continue;
+            }
+            view.set_readonly(arguments.readonly);
+            targets.push(file.target);
+            views.push(view);
+        }
+        if views.is_empty() {
+            let mut view = View::default();
+            view.set_settings(config.view);
+            views.push(view);
+        }
 
-        let args: Vec<String> = env::args().collect();
-        if let Some(file_path) = args.get(1) {
-            editor.view.load(file_path);
-            Terminal::set_title(&file_path)?;
+        backend.initialize()?;
+        let size = backend.size().unwrap_or_default();
+        let mut status_bar = StatusBar::default();
+        status_bar.set_format(config.status_format.clone());
+        let mut editor = Self{
+            should_quit: false,
+            views,
+            current_view: 0,
+            status_bar,
+            message_bar: MessageBar::default(),
+            command_bar: CommandBar::default(),
+            completion: Completion::default(),
+            kill_ring: KillRing::default(),
+            prompt_type: PromptType::None,
+            size,
+            keymap: config.keymap,
+            screen: Screen::default(),
+            last_frame: Screen::default(),
+            theme: config.theme.clone().with_depth(backend.colour_depth()),
+            last_click: None,
+            backend,
+            events,
+        };
+        editor.resize(size);
+        for (view, target) in editor.views.iter_mut().zip(targets) {
+            if let Some(target) = target {
+                view.goto(target);
+            }
         }
+        let title = editor.view().get_file_name();
+        editor.backend.set_title(&title)?;
 
-        editor.message_bar.set_message("HELP: Ctrl-S = save | Ctrl-Q = quit".to_string());
+        editor.message_bar.set_timeout(config.message_timeout);
+        // Every failure goes into the message history, with a count on screen when there are several
+        for err in &errors {
+            editor.message_bar.set_message(Severity::Error, err.clone());
+        }
+        if errors.is_empty() {
+            editor.message_bar.set_message(Severity::Info, "HELP: Ctrl-S = save | Ctrl-Q = quit".to_string());
+        }
+        else if errors.len() > 1 {
+            editor.message_bar.set_message(Severity::Error, format!("Could not open {} files, Alt-M lists them", errors.len()));
+        }
         Ok(editor)
     }
 
+    fn view(&self) -> &View {
+        &self.views[self.current_view]
+    }
+
+    fn view_mut(&mut self) -> &mut View {
+        &mut self.views[self.current_view]
+    }
+
+    fn handle_view_command(&mut self, command: EditorCommand) {
+        self.views[self.current_view].handle_command(command, &mut self.kill_ring);
+    }
+
+    fn switch_view(&mut self, forward: bool) {
+        let count = self.views.len();
+        self.current_view = if forward { (self.current_view + 1) % count } else { (self.current_view + count - 1) % count };
+        self.view_mut().set_redraw(true);
+        let file_name = self.view().get_file_name();
+        let _ = self.backend.set_title(&file_name);
+        self.message_bar.set_message(Severity::Info, format!("Buffer {}/{count}: {file_name}", self.current_view + 1));
+    }
+
     pub fn run(&mut self) {
         loop {
             self.refresh_screen();
             if self.should_quit {
                 break;
             }
-            match read() {
-                Ok(event) => self.evaluate_event(event),
+            // Wake up in time to clear the current message once it expires
+            if let Some(time_left) = self.message_bar.time_left(Instant::now()) {
+                match self.events.poll(time_left) {
+                    Ok(true) => (),
+                    Ok(false) => continue,
+                    Err(err) => {
+                        #[cfg(debug_assertions)]
+                        {
+                            panic!("Error: Could not poll for events {err:?}")
+                        }
+                    }
+                }
+            }
+            match self.events.read_event() {
+                Ok(Some(event)) => self.evaluate_event(event),
+                Ok(None) => break,
                 Err(err) => {
                     #[cfg(debug_assertions)]
                     {
@@ -74,60 +227,699 @@ impl Editor {
 
     pub fn resize(&mut self, size: Size) {
         self.size = size;
-        self.view.resize(Size{num_rows: size.num_rows - 2, num_columns: size.num_columns});
+        self.screen = Screen::new(size);
+        self.last_frame = Screen::new(size);
+        let _ = self.backend.clear_screen();
+        for view in &mut self.views {
+            view.resize(Size{num_rows: size.num_rows.saturating_sub(2), num_columns: size.num_columns});
+        }
         self.status_bar.resize(Size{num_rows: 1, num_columns: size.num_columns});
         self.message_bar.resize(Size{num_rows: 1, num_columns: size.num_columns});
+        self.command_bar.resize(Size{num_rows: 1, num_columns: size.num_columns});
+        self.completion.resize(Size{num_rows: size.num_rows.saturating_sub(2), num_columns: size.num_columns});
+        self.completion.hide();
     }
 
     fn evaluate_event(&mut self, event: Event) {
         let should_process = match &event {
             Event::Key(KeyEvent{kind, ..}) => kind == &KeyEventKind::Press,
-            Event::Resize(_, _) => true,
+            Event::Resize(_, _) | Event::Mouse(_) => true,
             _ => false,
         };
         if should_process {
-            if let Ok(command) = EditorCommand::try_from(event) {
+            if let Ok(command) = self.keymap.translate(event) {
+                let command = self.detect_double_click(command);
                 if matches!(command, EditorCommand::Quit) {
                     self.should_quit = true;
                 }
                 else if let EditorCommand::Resize(size) = command {
                     self.resize(size);
-                    self.view.set_redraw(true);
+                }
+                else if self.prompt_type != PromptType::None {
+                    self.process_prompt_command(&command);
                 }
                 else {
-                    self.view.handle_command(command);
+                    self.process_command(command);
                 }
             }
         }
     }
 
+    // A second click on the same spot shortly after the first selects the word under it
+    fn detect_double_click(&mut self, command: EditorCommand) -> EditorCommand {
+        let EditorCommand::Mouse(MouseAction::Click, position) = command else {
+            return command;
+        };
+        let now = Instant::now();
+        let is_double = self.last_click.is_some_and(|(time, last)| last == position && now.duration_since(time) < DOUBLE_CLICK_TIME);
+        self.last_click = if is_double { None } else { Some((now, position)) };
+        if is_double { EditorCommand::Mouse(MouseAction::DoubleClick, position) } else { command }
+    }
+
+    fn process_command(&mut self, command: EditorCommand) {
+        if self.completion.is_visible() && self.process_completion_command(command) {
+            return;
+        }
+        match command {
+            EditorCommand::Mouse(_, position) if position.row >= self.size.num_rows.saturating_sub(2) => (),
+            EditorCommand::GotoLine => self.start_prompt(PromptType::GotoLine),
+            EditorCommand::NextBuffer => self.switch_view(true),
+            EditorCommand::PreviousBuffer => self.switch_view(false),
+            EditorCommand::ShowMessages => self.show_messages(),
+            _ if self.view().is_readonly() && (command.is_edit() || matches!(command, EditorCommand::Save)) => {
+                self.message_bar.set_message(Severity::Warning, "File is read-only".to_string());
+            },
+            EditorCommand::ToggleComment if self.view().file_type().line_comment().is_none() => {
+                let file_type = self.view().file_type().name();
+                self.message_bar.set_message(Severity::Warning, format!("{file_type} has no line comments"));
+            },
+            EditorCommand::Complete | EditorCommand::Insert('\t') => self.start_completion(command),
+            EditorCommand::Save if !self.view().has_file_path() => self.start_prompt(PromptType::SaveAs),
+            EditorCommand::Save => self.save(None),
+            _ => self.handle_view_command(command),
+        }
+    }
+
+    // Tab only opens the list when there are words to offer, and otherwise indents as usual
+    fn start_completion(&mut self, command: EditorCommand) {
+        if self.show_completion() {
+            return;
+        }
+        match command {
+            EditorCommand::Complete => self.message_bar.set_message(Severity::Info, "No completions".to_string()),
+            _ => self.handle_view_command(command),
+        }
+    }
+
+    fn show_completion(&mut self) -> bool {
+        let Some((prefix, anchor)) = self.view().completion_prefix() else {
+            return false;
+        };
+        let mut candidates = Candidates::default();
+        for (index, view) in self.views.iter().enumerate() {
+            view.collect_words(&prefix, index == self.current_view, &mut candidates);
+        }
+        self.completion.show(candidates.ranked(), &prefix, anchor);
+        self.completion.is_visible()
+    }
+
+    // Typing more of the word filters the words already collected, which are only gathered again once it gets shorter
+    fn narrow_completion(&mut self) {
+        let Some((prefix, anchor)) = self.view().completion_prefix() else {
+            return;
+        };
+        if !self.completion.narrow(&prefix, anchor) {
+            self.show_completion();
+        }
+    }
+
+    fn hide_completion(&mut self) {
+        self.completion.hide();
+        self.view_mut().set_redraw(true);
+    }
+
+    // While the list is open the arrows pick a word and Enter or Tab accepts it, and typing narrows it down
+    fn process_completion_command(&mut self, command: EditorCommand) -> bool {
+        match command {
+            EditorCommand::Move(Direction::Up) => self.completion.select(false),
+            EditorCommand::Move(Direction::Down) => self.completion.select(true),
+            EditorCommand::Enter | EditorCommand::Insert('\t') => {
+                if let Some(word) = self.completion.selected().map(str::to_string) {
+                    self.view_mut().complete(&word);
+                }
+                self.hide_completion();
+            },
+            EditorCommand::Dismiss => self.hide_completion(),
+            EditorCommand::Insert(_) | EditorCommand::Backspace => {
+                self.hide_completion();
+                self.process_command(command);
+                self.narrow_completion();
+            },
+            _ => {
+                self.hide_completion();
+                return false;
+            },
+        }
+        true
+    }
+
+    // Opens the message history in a read-only buffer, reusing it if it is already open
+    fn show_messages(&mut self) {
+        let name = "[Messages]";
+        let view = View::scratch(name, &self.message_bar.history(), self.view().settings());
+        if let Some(index) = self.views.iter().position(|view| view.scratch_name() == Some(name)) {
+            self.views[index] = view;
+            self.current_view = index;
+        }
+        else {
+            self.views.push(view);
+            self.current_view = self.views.len() - 1;
+        }
+        let Size{num_rows, num_columns} = self.size;
+        self.view_mut().resize(Size{num_rows: num_rows.saturating_sub(2), num_columns});
+        self.handle_view_command(EditorCommand::Move(Direction::BufferEnd));
+        let _ = self.backend.set_title(name);
+    }
+
+    fn save(&mut self, file_path: Option<&str>) {
+        match self.view_mut().save_file(file_path) {
+            Ok(()) => {
+                let file_name = self.view().get_file_name();
+                let _ = self.backend.set_title(&file_name);
+                self.message_bar.set_message(Severity::Info, format!("Saved {file_name}"));
+            },
+            Err(err) => {
+                let message = format!("Could not save {}: {err}", self.view().get_file_name());
+                self.message_bar.set_message(Severity::Error, message);
+            },
+        }
+    }
+
+    fn start_prompt(&mut self, prompt_type: PromptType) {
+        let prompt = match prompt_type {
+            PromptType::GotoLine => "Go to line[:column], +N, -N or N%: ",
+            PromptType::SaveAs => "Save as: ",
+            PromptType::None => return,
+        };
+        self.command_bar.set_prompt(prompt);
+        self.prompt_type = prompt_type;
+    }
+
+    fn end_prompt(&mut self) {
+        self.prompt_type = PromptType::None;
+        self.message_bar.set_redraw(true);
+    }
+
+    fn process_prompt_command(&mut self, command: &EditorCommand) {
+        match command {
+            EditorCommand::Enter => {
+                let prompt_type = self.prompt_type;
+                self.end_prompt();
+                let value = self.command_bar.get_value().to_string();
+                match prompt_type {
+                    PromptType::GotoLine => match value.parse::<GotoTarget>() {
+                        Ok(target) => self.view_mut().goto(target),
+                        Err(err) => self.message_bar.set_message(Severity::Error, err),
+                    },
+                    PromptType::SaveAs if value.trim().is_empty() => {
+                        self.message_bar.set_message(Severity::Warning, "Save aborted".to_string());
+                    },
+                    PromptType::SaveAs => self.save(Some(value.trim())),
+                    PromptType::None => (),
+                }
+            },
+            EditorCommand::Dismiss | EditorCommand::ClearMark => self.end_prompt(),
+            _ => self.command_bar.handle_command(command),
+        }
+    }
+
     fn refresh_screen(&mut self) {
+        self.message_bar.expire(Instant::now());
         if self.size.num_rows == 0 || self.size.num_columns == 0 {
             return;
         }
-        let _ = Terminal::hide_cursor();
-        self.status_bar.set_status(self.view.get_status());
+        let _ = self.backend.hide_cursor();
+        let mut status = self.view().get_status();
+        if self.prompt_type != PromptType::None {
+            status.mode = Mode::Prompt;
+        }
+        self.status_bar.set_status(status);
         if self.size.num_rows > 0 {
-            self.message_bar.render(self.size.num_rows - 1);
+            if self.prompt_type == PromptType::None {
+                self.message_bar.render(&mut self.screen, &self.theme, self.size.num_rows - 1);
+            }
+            else {
+                self.command_bar.render(&mut self.screen, &self.theme, self.size.num_rows - 1);
+            }
         }
         if self.size.num_rows > 1 {
-            self.status_bar.render(self.size.num_rows - 2);
+            self.status_bar.render(&mut self.screen, &self.theme, self.size.num_rows - 2);
         }
         if self.size.num_rows > 2 {
-            self.view.render(0);
+            self.views[self.current_view].render(&mut self.screen, &self.theme, 0);
+            // The list is drawn over the view, so it goes again whenever the view does
+            if self.completion.is_visible() {
+                self.completion.set_redraw(true);
+                self.completion.render(&mut self.screen, &self.theme, 0);
+            }
         }
-        let _ = Terminal::move_cursor_to(self.view.get_cursor_position());
-        let _ = Terminal::show_cursor();
-        let _ = Terminal::execute();
+        let _ = self.backend.render(&self.last_frame, &self.screen);
+        self.last_frame.clone_from(&self.screen);
+        let cursor_position = if self.prompt_type == PromptType::None {
+            self.view().get_cursor_position()
+        }
+        else {
+            Some(Position{row: self.size.num_rows - 1, column: self.command_bar.get_cursor_column()})
+        };
+        if let Some(cursor_position) = cursor_position {
+            let _ = self.backend.move_cursor_to(cursor_position);
+            let _ = self.backend.show_cursor();
+        }
+        let _ = self.backend.execute();
     }
 
 }
 
 impl Drop for Editor {
     fn drop(&mut self){
-        let _ = Terminal::terminate();
+        let _ = self.backend.terminate();
         if self.should_quit {
-            let _ = Terminal::print("Goodbye!\r\n");
+            let _ = self.backend.print("Goodbye!\r\n");
+            let _ = self.backend.execute();
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::config::Config;
+    use crate::keymap::Keymap;
+    use crate::terminal::Position;
+    use crate::testing::{ctrl, edit, key, mouse, run, temp_dir, text};
+    use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEventKind};
+    use std::fs;
+
+    #[test]
+    fn shows_file_contents_and_status() {
+        let dir = temp_dir("shows_file_contents");
+        let path = dir.join("notes.txt");
+        fs::write(&path, "first line\nsecond line\n").unwrap();
+        let screen = run(&[path.to_str().unwrap()], &Config::default(), Vec::new());
+        assert_eq!(screen.row(0).trim_end(), "first line");
+        assert_eq!(screen.row(1).trim_end(), "second line");
+        assert!(screen.row(8).contains("2 lines"));
+        assert!(screen.row(9).starts_with("HELP:"));
+        assert_eq!(screen.title(), path.to_str().unwrap());
+    }
+
+    #[test]
+    fn typed_text_is_saved() {
+        let dir = temp_dir("typed_text_is_saved");
+        let path = dir.join("new.txt");
+        let mut events = text("hello\nworld");
+        events.push(ctrl('s'));
+        let screen = run(&[path.to_str().unwrap()], &Config::default(), events);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello\nworld\n");
+        assert!(screen.row(9).starts_with("Saved"));
+        assert!(!screen.row(8).contains("modified"));
+    }
+
+    #[test]
+    fn cursor_follows_typing() {
+        let screen = run(&[], &Config::default(), text("abc"));
+        let cursor = screen.cursor().unwrap();
+        assert_eq!((cursor.row, cursor.column), (0, 3));
+        assert_eq!(screen.row(0).trim_end(), "abc");
+    }
+
+    #[test]
+    fn quitting_says_goodbye() {
+        let screen = run(&[], &Config::default(), vec![ctrl('q')]);
+        assert_eq!(screen.output(), "Goodbye!\r\n");
+    }
+
+    #[test]
+    fn message_history_opens_in_a_read_only_buffer() {
+        let dir = temp_dir("message_history");
+        let path = dir.join("log.txt");
+        let mut events = text("x");
+        events.push(ctrl('s'));
+        events.push(key(KeyCode::Char('m'), KeyModifiers::ALT));
+        events.extend(text("y"));
+        let screen = run(&[path.to_str().unwrap()], &Config::default(), events);
+        assert_eq!(screen.title(), "[Messages]");
+        assert!(screen.row(0).starts_with("info: HELP"));
+        assert!(screen.row(1).starts_with("info: Saved"));
+        assert!(screen.row(9).starts_with("File is read-only"));
+    }
+
+    #[test]
+    fn clicks_account_for_the_gutter() {
+        let dir = temp_dir("clicks_account_for_the_gutter");
+        let path = dir.join("click.txt");
+        fs::write(&path, "first\nsecond line\n").unwrap();
+        let mut config = Config::default();
+        config.view.line_numbers = crate::config::LineNumbers::Absolute;
+        let screen = run(&[path.to_str().unwrap()], &config, vec![mouse(MouseEventKind::Down(MouseButton::Left), 1, 5)]);
+        let cursor = screen.cursor().unwrap();
+        assert_eq!((cursor.row, cursor.column), (1, 5));
+        assert!(screen.row(8).contains("Col 4"));
+    }
+
+    #[test]
+    fn drag_and_double_click_select_text() {
+        let dir = temp_dir("drag_and_double_click");
+        let path = dir.join("select.txt");
+        fs::write(&path, "hello brave world\n").unwrap();
+        let file = path.to_str().unwrap();
+        let events = vec![
+            mouse(MouseEventKind::Down(MouseButton::Left), 0, 2),
+            mouse(MouseEventKind::Drag(MouseButton::Left), 0, 9),
+        ];
+        assert!(run(&[file], &Config::default(), events).row(8).contains("7 selected"));
+        let events = vec![
+            mouse(MouseEventKind::Down(MouseButton::Left), 0, 8),
+            mouse(MouseEventKind::Down(MouseButton::Left), 0, 8),
+        ];
+        assert!(run(&[file], &Config::default(), events).row(8).contains("5 selected"));
+    }
+
+    #[test]
+    fn wheel_scrolls_without_moving_the_cursor() {
+        let dir = temp_dir("wheel_scrolls");
+        let path = dir.join("long.txt");
+        let contents = (1..=30).map(|line| format!("line {line}")).collect::<Vec<_>>().join("\n");
+        fs::write(&path, contents).unwrap();
+        let screen = run(&[path.to_str().unwrap()], &Config::default(), vec![mouse(MouseEventKind::ScrollDown, 0, 0)]);
+        assert_eq!(screen.row(0).trim_end(), "line 4");
+        assert!(screen.row(8).contains("Ln 1,"));
+        assert!(screen.cursor().is_none());
+    }
+
+    fn long_file(name: &str) -> String {
+        let path = temp_dir(name).join("long.txt");
+        let contents = (1..=30).map(|line| format!("line {line}")).collect::<Vec<_>>().join("\n");
+        fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn scroll_off_keeps_lines_below_the_cursor() {
+        let path = long_file("scroll_off");
+        let mut config = Config::default();
+        config.view.scroll_off = 2;
+        let screen = run(&[&path], &config, vec![key(KeyCode::Down, KeyModifiers::NONE); 7]);
+        assert_eq!(screen.row(0).trim_end(), "line 3");
+        assert_eq!(screen.cursor().map(|position| position.row), Some(5));
+    }
+
+    #[test]
+    fn line_scrolling_drags_the_cursor_along_at_the_edge() {
+        let path = long_file("scroll_lines");
+        let screen = run(&[&path], &Config::default(), vec![ctrl('e'), ctrl('e'), ctrl('e')]);
+        assert_eq!(screen.row(0).trim_end(), "line 4");
+        assert!(screen.row(8).contains("Ln 4,"));
+        let screen = run(&[&path], &Config::default(), vec![key(KeyCode::PageDown, KeyModifiers::ALT), key(KeyCode::PageDown, KeyModifiers::ALT), key(KeyCode::PageUp, KeyModifiers::ALT)]);
+        assert_eq!(screen.row(0).trim_end(), "line 5");
+        assert!(screen.row(8).contains("Ln 9,"));
+    }
+
+    #[test]
+    fn recenter_cycles_between_middle_top_and_bottom() {
+        let path = long_file("recenter");
+        let mut events = vec![key(KeyCode::Down, KeyModifiers::NONE); 10];
+        for (presses, top) in [(1, "line 7"), (2, "line 11"), (3, "line 4"), (4, "line 7")] {
+            events.push(ctrl('l'));
+            let screen = run(&[&path], &Config::default(), events.clone());
+            assert_eq!(screen.row(0).trim_end(), top, "after {presses} presses");
+        }
+    }
+
+    #[test]
+    fn vertical_movement_remembers_the_display_column() {
+        let path = temp_dir("sticky_column").join("columns.txt");
+        fs::write(&path, "abcdef\nx\n日本語\nabcdef\n").unwrap();
+        let path = path.to_str().unwrap();
+        let mut events = vec![key(KeyCode::Right, KeyModifiers::NONE); 4];
+        events.extend(vec![key(KeyCode::Down, KeyModifiers::NONE); 2]);
+        assert!(run(&[path], &Config::default(), events.clone()).row(8).contains("Ln 3, Col 5"));
+        events.push(key(KeyCode::Down, KeyModifiers::NONE));
+        assert!(run(&[path], &Config::default(), events.clone()).row(8).contains("Ln 4, Col 5"));
+        events.extend([key(KeyCode::Up, KeyModifiers::NONE), key(KeyCode::Up, KeyModifiers::NONE), key(KeyCode::Left, KeyModifiers::NONE), key(KeyCode::Down, KeyModifiers::NONE)]);
+        assert!(run(&[path], &Config::default(), events).row(8).contains("Ln 3, Col 1"));
+    }
+
+    #[test]
+    fn enter_indents_and_closing_braces_dedent() {
+        let dir = temp_dir("auto_indent");
+        let rust = dir.join("main.rs");
+        let mut config = Config::default();
+        config.view.soft_tabs = true;
+        let mut events = text("fn main() {\nlet a = 1;   \n}");
+        events.push(ctrl('s'));
+        run(&[rust.to_str().unwrap()], &config, events);
+        assert_eq!(fs::read_to_string(&rust).unwrap(), "fn main() {\n    let a = 1;\n}\n");
+        let python = dir.join("main.py");
+        let mut events = text("if x:\npass\n\nx");
+        events.push(ctrl('s'));
+        run(&[python.to_str().unwrap()], &Config::default(), events);
+        assert_eq!(fs::read_to_string(&python).unwrap(), "if x:\n\tpass\n\n\tx\n");
+        let file = rust.to_str().unwrap();
+        let mut config = Config::default();
+        config.view.auto_pairs = true;
+        assert_eq!(edit(&[file], "", text("fn f() {\nx"), &config), "fn f() {\n\tx\n}\n");
+        let events = [text("    {}"), vec![key(KeyCode::Left, KeyModifiers::NONE)], text("\nx")].concat();
+        assert_eq!(edit(&[file], "", events, &Config::default()), "    {\n    \tx\n    }\n");
+    }
+
+    #[test]
+    fn block_edits_keep_the_selection_and_undo_in_one_step() {
+        let path = temp_dir("block_edits").join("main.rs");
+        let file = path.to_str().unwrap();
+        let select = vec![
+            mouse(MouseEventKind::Down(MouseButton::Left), 0, 1),
+            mouse(MouseEventKind::Drag(MouseButton::Left), 1, 2),
+        ];
+        let cases = [
+            (vec![key(KeyCode::Tab, KeyModifiers::NONE), ctrl('7')], "\t// a();\n\t// b();\nc();\n", true),
+            (vec![key(KeyCode::Tab, KeyModifiers::NONE), ctrl('7'), ctrl('z')], "\ta();\n\tb();\nc();\n", false),
+            (vec![ctrl('7'), ctrl('7'), key(KeyCode::BackTab, KeyModifiers::NONE)], "a();\nb();\nc();\n", true),
+            (vec![ctrl('7'), ctrl('z'), ctrl('r')], "// a();\n// b();\nc();\n", false),
+        ];
+        for (events, expected, keeps_selection) in cases {
+            fs::write(&path, "a();\nb();\nc();\n").unwrap();
+            let mut all = select.clone();
+            all.extend(events);
+            all.push(ctrl('s'));
+            let screen = run(&[file], &Config::default(), all);
+            assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+            assert_eq!(screen.row(8).contains("selected"), keeps_selection);
+        }
+    }
+
+    #[test]
+    fn typing_is_undone_a_word_at_a_time() {
+        let path = temp_dir("undo_typing").join("notes.txt");
+        let mut events = text("hello world");
+        events.extend([ctrl('z'), ctrl('s')]);
+        run(&[path.to_str().unwrap()], &Config::default(), events);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello \n");
+    }
+
+    #[test]
+    fn line_operations_edit_whole_lines() {
+        let path = temp_dir("line_operations").join("lines.txt");
+        let file = path.to_str().unwrap();
+        let alt = |code| key(code, KeyModifiers::ALT);
+        let down = key(KeyCode::Down, KeyModifiers::NONE);
+        let select_all = vec![
+            mouse(MouseEventKind::Down(MouseButton::Left), 0, 0),
+            mouse(MouseEventKind::Drag(MouseButton::Left), 2, 1),
+        ];
+        let cases = [
+            ("b\na\nc\n", vec![alt(KeyCode::Down)], "a\nb\nc\n"),
+            ("b\na\nc\n", vec![down.clone(), down.clone(), alt(KeyCode::Up)], "b\nc\na\n"),
+            ("b\na\nc\n", vec![key(KeyCode::Char('D'), KeyModifiers::ALT | KeyModifiers::SHIFT)], "b\nb\na\nc\n"),
+            ("b\na\nc\n", vec![alt(KeyCode::Char('k'))], "a\nc\n"),
+            ("  x  \n   y\n", vec![alt(KeyCode::Char('j'))], "  x y\n"),
+            ("b\na\nc\n", [select_all.clone(), vec![alt(KeyCode::Char('s'))]].concat(), "a\nb\nc\n"),
+            ("b\na\nc\n", [select_all, vec![alt(KeyCode::Char('s')), ctrl('z')]].concat(), "b\na\nc\n"),
+        ];
+        for (contents, events, expected) in cases {
+            assert_eq!(edit(&[file], contents, events, &Config::default()), expected);
+        }
+    }
+
+    #[test]
+    fn matching_brackets_are_highlighted_and_jumped_to() {
+        let path = temp_dir("matching_brackets").join("main.rs");
+        fs::write(&path, "f(a[0]) {\n}\n").unwrap();
+        let file = path.to_str().unwrap();
+        let right = key(KeyCode::Right, KeyModifiers::NONE);
+        let screen = run(&[file], &Config::default(), vec![right.clone()]);
+        assert!(screen.style(Position{row: 0, column: 6}).reverse);
+        assert!(!screen.style(Position{row: 0, column: 5}).reverse);
+        let screen = run(&[file], &Config::default(), vec![right.clone(), ctrl('5')]);
+        assert!(screen.row(8).contains("Ln 1, Col 7"));
+        let screen = run(&[file], &Config::default(), vec![key(KeyCode::End, KeyModifiers::NONE), ctrl(']')]);
+        assert!(screen.row(8).contains("Ln 2, Col 1"));
+    }
+
+    #[test]
+    fn brackets_and_quotes_are_paired() {
+        let path = temp_dir("auto_pairs").join("pairs.txt");
+        let file = path.to_str().unwrap();
+        let mut config = Config::default();
+        config.view.auto_pairs = true;
+        let cases = [
+            ("", text("f(x"), "f(x)\n"),
+            ("", text("f(x) [y]"), "f(x) [y]\n"),
+            ("", text("don't say \"hi\""), "don't say \"hi\"\n"),
+            ("x\n", text("("), "(x\n"),
+            ("", [text("a{"), vec![key(KeyCode::Backspace, KeyModifiers::NONE)]].concat(), "a\n"),
+            ("", [text("(("), vec![key(KeyCode::Left, KeyModifiers::NONE), key(KeyCode::Right, KeyModifiers::NONE)], text("))")].concat(), "(())))\n"),
+            (")\n", text("x)"), "x))\n"),
+            ("()\n", vec![key(KeyCode::Right, KeyModifiers::NONE), key(KeyCode::Backspace, KeyModifiers::NONE)], ")\n"),
+            ("", [text("[a"), vec![key(KeyCode::Backspace, KeyModifiers::NONE), key(KeyCode::Backspace, KeyModifiers::NONE)], text("]")].concat(), "]\n"),
+        ];
+        for (contents, events, expected) in cases {
+            assert_eq!(edit(&[file], contents, events, &config), expected);
+        }
+    }
+
+    #[test]
+    fn secondary_selections_are_highlighted_on_wrapped_rows() {
+        let path = temp_dir("secondary_selections").join("wrapped.txt");
+        fs::write(&path, format!("foo {} foo foo\n", "a".repeat(74))).unwrap();
+        let mut config = Config::default();
+        config.view.wrap = crate::config::WrapMode::Word;
+        let screen = run(&[path.to_str().unwrap()], &config, vec![ctrl('d'), ctrl('d'), ctrl('d')]);
+        assert!(screen.row(1).starts_with("foo foo"));
+        for column in 0..3 {
+            assert!(screen.style(Position{row: 0, column}).reverse);
+            assert!(screen.style(Position{row: 1, column}).reverse);
+        }
+        assert!(!screen.style(Position{row: 0, column: 4}).reverse);
+    }
+
+    #[test]
+    fn edits_apply_at_every_cursor() {
+        let path = temp_dir("multiple_cursors").join("cursors.txt");
+        let file = path.to_str().unwrap();
+        let below = key(KeyCode::Down, KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let cases = [
+            ("ab\ncd\nef\n", [vec![below.clone(), below.clone()], text("x")].concat(), "xab\nxcd\nxef\n"),
+            ("abc\nde\nefg\n", vec![key(KeyCode::Right, KeyModifiers::NONE), below.clone(), below.clone(), key(KeyCode::Delete, KeyModifiers::NONE)], "ac\nd\neg\n"),
+            ("ab\ncd\n", [vec![below.clone(), key(KeyCode::End, KeyModifiers::NONE)], text("!\n")].concat(), "ab!\n\ncd!\n\n"),
+            ("ab\ncd\n", [vec![below, key(KeyCode::Esc, KeyModifiers::NONE)], text("x")].concat(), "ab\nxcd\n"),
+            ("foo bar foo\nfoo\n", [vec![ctrl('d'), ctrl('d'), ctrl('d')], text("x")].concat(), "foox bar foox\nfoox\n"),
+            ("foo foo\n", [vec![ctrl('d'), ctrl('d'), ctrl('d')], text("x")].concat(), "foox foox\n"),
+        ];
+        for (contents, events, expected) in cases {
+            assert_eq!(edit(&[file], contents, events, &Config::default()), expected);
+        }
+    }
+
+    #[test]
+    fn block_selections_edit_a_rectangle_of_columns() {
+        let path = temp_dir("block_selection").join("table.txt");
+        let file = path.to_str().unwrap();
+        let block = key(KeyCode::Char('r'), KeyModifiers::ALT);
+        let [right, down] = [KeyCode::Right, KeyCode::Down].map(|code| key(code, KeyModifiers::NONE));
+        let cases = [
+            ("abc\ndef\nghi\n", vec![right.clone(), block.clone(), down.clone(), down.clone(), right.clone(), ctrl('x')], "ac\ndf\ngi\n"),
+            ("abc\ndef\nghi\n", vec![right.clone(), block.clone(), down.clone(), down.clone(), right.clone(), ctrl('x'),
+                key(KeyCode::Home, KeyModifiers::CONTROL), ctrl('v')], "bac\nedf\nhgi\n"),
+            ("abc\na\nabc\n", [vec![right.clone(), right.clone(), block.clone(), down.clone(), down.clone()], text("|")].concat(), "ab|c\na |\nab|c\n"),
+            ("abc\na\nabc\n", [vec![right.clone(), right.clone(), block.clone(), down.clone(), down.clone()], text("||"), vec![ctrl('z')]].concat(), "abc\na\nabc\n"),
+            ("a\tb\nabcde\n", vec![right.clone(), block.clone(), down.clone(), right.clone(), key(KeyCode::Delete, KeyModifiers::NONE)], "ab\nacde\n"),
+            ("abc\ndef\n", vec![right.clone(), right.clone(), block, down, key(KeyCode::Backspace, KeyModifiers::NONE)], "ac\ndf\n"),
+        ];
+        for (contents, events, expected) in cases {
+            assert_eq!(edit(&[file], contents, events, &Config::default()), expected);
+        }
+    }
+
+    #[test]
+    fn words_are_completed_from_open_buffers() {
+        let dir = temp_dir("completion");
+        let path = dir.join("words.txt");
+        let other = dir.join("other.txt");
+        fs::write(&other, "fortune\n").unwrap();
+        let (file, other) = (path.to_str().unwrap(), other.to_str().unwrap());
+        let [down, enter, tab, esc] = [KeyCode::Down, KeyCode::Enter, KeyCode::Tab, KeyCode::Esc].map(|code| key(code, KeyModifiers::NONE));
+        let open = [down.clone(), key(KeyCode::End, KeyModifiers::NONE), tab.clone()];
+        let cases = [
+            ("forward\nfoo fo\n", [open.to_vec(), vec![enter.clone()]].concat(), "forward\nfoo foo\n"),
+            ("forward\nfoo fo\n", [open.to_vec(), vec![down.clone(), enter.clone()]].concat(), "forward\nfoo forward\n"),
+            ("forward\nfoo fo\n", [open.to_vec(), vec![down.clone(), down.clone(), tab.clone()]].concat(), "forward\nfoo fortune\n"),
+            ("forward\nformat fo\n", [open.to_vec(), text("rm"), vec![enter.clone()]].concat(), "forward\nformat format\n"),
+            ("forward\nfoo fo\n", [open.to_vec(), vec![esc], text("x")].concat(), "forward\nfoo fox\n"),
+            ("forward\nfoo for\n", [open.to_vec(), vec![key(KeyCode::Backspace, KeyModifiers::NONE), enter]].concat(), "forward\nfoo foo\n"),
+            ("forward\nzz\n", open.to_vec(), "forward\nzz\t\n"),
+        ];
+        for (contents, events, expected) in cases {
+            assert_eq!(edit(&[file, other], contents, events, &Config::default()), expected);
+        }
+        fs::write(&path, "forward\nfoo fo\n").unwrap();
+        let screen = run(&[file], &Config::default(), open.to_vec());
+        assert_eq!(screen.row(2).find(" foo     "), Some(3));
+        assert_eq!(screen.row(3).find(" forward "), Some(3));
+        assert!(screen.style(Position{row: 3, column: 3}).reverse);
+    }
+
+    #[test]
+    fn emacs_kills_collect_into_the_kill_ring() {
+        let path = temp_dir("kill_ring").join("kills.txt");
+        let file = path.to_str().unwrap();
+        let mut config = Config::default();
+        config.keymap = Keymap::Emacs;
+        let yank_pop = key(KeyCode::Char('y'), KeyModifiers::ALT);
+        let cases = [
+            ("one\ntwo\n", vec![ctrl('k'), ctrl('k'), ctrl('e'), ctrl('y')], "twoone\n\n"),
+            ("a\nb\n", vec![ctrl('k'), ctrl('n'), ctrl('k'), ctrl('y')], "\nb\n"),
+            ("a\nb\n", vec![ctrl('k'), ctrl('n'), ctrl('k'), ctrl('y'), yank_pop.clone()], "\na\n"),
+            ("a\nb\n", vec![ctrl('k'), ctrl('n'), ctrl('k'), ctrl('y'), yank_pop.clone(), yank_pop], "\nb\n"),
+        ];
+        for (contents, events, expected) in cases {
+            assert_eq!(edit(&[file], contents, events, &config), expected);
+        }
+    }
+
+    #[test]
+    fn copies_paste_into_other_buffers() {
+        let dir = temp_dir("shared_kill_ring");
+        let (first, second) = (dir.join("a.txt"), dir.join("b.txt"));
+        fs::write(&first, "alpha\n").unwrap();
+        fs::write(&second, "beta\n").unwrap();
+        let events = vec![
+            mouse(MouseEventKind::Down(MouseButton::Left), 0, 0),
+            mouse(MouseEventKind::Drag(MouseButton::Left), 0, 5),
+            ctrl('c'),
+            key(KeyCode::PageDown, KeyModifiers::CONTROL),
+            ctrl('v'),
+            ctrl('s'),
+        ];
+        run(&[first.to_str().unwrap(), second.to_str().unwrap()], &Config::default(), events);
+        assert_eq!(fs::read_to_string(&second).unwrap(), "alphabeta\n");
+    }
+
+    #[test]
+    fn unreadable_files_are_reported_and_not_opened() {
+        let dir = temp_dir("unreadable_files");
+        let (first, second) = (dir.join("first"), dir.join("second"));
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+        let screen = run(&[first], &Config::default(), Vec::new());
+        assert!(screen.row(9).starts_with(&format!("Could not open {first}: ")));
+        assert!(screen.row(8).contains("[No Name]"));
+        let screen = run(&[first, second], &Config::default(), vec![key(KeyCode::Char('m'), KeyModifiers::ALT)]);
+        assert!(screen.row(0).contains(&format!("Could not open {first}")));
+        assert!(screen.row(1).contains(&format!("Could not open {second}")));
+        assert!(screen.row(2).contains("Could not open 2 files"));
+    }
+
+    #[test]
+    fn failed_save_as_asks_for_a_name_again() {
+        let bad_path = temp_dir("failed_save_as").join("missing").join("file.txt");
+        let mut events = text("x");
+        events.push(ctrl('s'));
+        events.extend(text(bad_path.to_str().unwrap()));
+        events.extend([key(KeyCode::Enter, KeyModifiers::NONE), ctrl('s')]);
+        let screen = run(&[], &Config::default(), events);
+        assert!(screen.row(9).starts_with("Save as: "));
+        assert!(!bad_path.exists());
+    }
+
+    #[test]
+    fn status_column_counts_display_columns() {
+        let dir = temp_dir("status_column_counts_display_columns");
+        let path = dir.join("columns.txt");
+        fs::write(&path, "\t漢x\n").unwrap();
+        let screen = run(&[path.to_str().unwrap()], &Config::default(), vec![key(KeyCode::End, KeyModifiers::NONE)]);
+        assert!(screen.row(8).contains("Ln 1, Col 8"));
+    }
+
 }
\ No newline at end of file
@@ -1,8 +1,17 @@
-use crate::terminal::Size;
+use crate::terminal::{Size, Position};
 
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use std::convert::TryFrom;
 
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum MouseAction {
+    Click,
+    DoubleClick,
+    Drag,
+    AddCursor,
+}
+
+#[derive(Copy, Clone)]
 pub enum Direction {
     PageUp,
     PageDown,
@@ -12,21 +21,73 @@ pub enum Direction {
     Down,
     Left,
     Right,
+    WordLeft,
+    WordRight,
+    BufferStart,
+    BufferEnd,
 }
 
-
-// TODO: Implement long backspace
+#[derive(Copy, Clone)]
 pub enum EditorCommand {
     Move(Direction),
     Insert(char),
     Backspace,
     Delete,
+    DeleteWordBackward,
+    DeleteWordForward,
+    Dedent,
+    ToggleComment,
+    DuplicateLines,
+    MoveLinesUp,
+    MoveLinesDown,
+    DeleteLines,
+    JoinLines,
+    SortLines,
+    JumpToBracket,
+    AddCursorAbove,
+    AddCursorBelow,
+    AddNextOccurrence,
+    Complete,
     Enter,
+    KillLine,
+    KillRegion,
+    CopyRegion,
+    Yank,
+    YankPop,
+    Undo,
+    Redo,
+    SetMark,
+    ToggleBlockSelection,
+    ClearMark,
+    GotoLine,
+    Dismiss,
+    NextBuffer,
+    PreviousBuffer,
+    ToggleLineNumbers,
+    ToggleWrap,
+    ToggleWhitespace,
+    ShowMessages,
+    Mouse(MouseAction, Position),
+    Scroll(isize),
+    ScrollLines(isize),
+    ScrollHalfPage(isize),
+    Recenter,
     Resize(Size),
     Save,
     Quit,
 }
 
+impl EditorCommand {
+
+    pub fn is_edit(&self) -> bool {
+        matches!(self, Self::Insert(_) | Self::Backspace | Self::Delete | Self::DeleteWordBackward |
+            Self::DeleteWordForward | Self::Dedent | Self::ToggleComment | Self::DuplicateLines | Self::MoveLinesUp |
+            Self::MoveLinesDown | Self::DeleteLines | Self::JoinLines | Self::SortLines | Self::Complete | Self::Enter | Self::KillLine | Self::KillRegion |
+            Self::Yank | Self::YankPop | Self::Undo | Self::Redo)
+    }
+
+}
+
 impl TryFrom<Event> for EditorCommand {
     type Error = String;
     fn try_from(event: Event) -> Result<Self, Self::Error> {
@@ -35,6 +96,24 @@ impl TryFrom<Event> for EditorCommand {
                 code, modifiers, ..
             }) => match(code, modifiers) {
                 (KeyCode::Char('q'), KeyModifiers::CONTROL) => Ok(Self::Quit),
+                (KeyCode::Left, KeyModifiers::CONTROL) => Ok(Self::Move(Direction::WordLeft)),
+                (KeyCode::Right, KeyModifiers::CONTROL) => Ok(Self::Move(Direction::WordRight)),
+                (KeyCode::Home, KeyModifiers::CONTROL) => Ok(Self::Move(Direction::BufferStart)),
+                (KeyCode::End, KeyModifiers::CONTROL) => Ok(Self::Move(Direction::BufferEnd)),
+                (KeyCode::Backspace, KeyModifiers::CONTROL | KeyModifiers::ALT) | (KeyCode::Char('h'), KeyModifiers::CONTROL) => Ok(Self::DeleteWordBackward),
+                (KeyCode::Delete, KeyModifiers::CONTROL) => Ok(Self::DeleteWordForward),
+                (KeyCode::PageDown, KeyModifiers::CONTROL) => Ok(Self::NextBuffer),
+                (KeyCode::PageUp, KeyModifiers::CONTROL) => Ok(Self::PreviousBuffer),
+                (KeyCode::PageDown, KeyModifiers::ALT) => Ok(Self::ScrollHalfPage(1)),
+                (KeyCode::PageUp, KeyModifiers::ALT) => Ok(Self::ScrollHalfPage(-1)),
+                (KeyCode::Up, modifiers) if modifiers == KeyModifiers::ALT | KeyModifiers::SHIFT => Ok(Self::AddCursorAbove),
+                (KeyCode::Down, modifiers) if modifiers == KeyModifiers::ALT | KeyModifiers::SHIFT => Ok(Self::AddCursorBelow),
+                (KeyCode::Char('d' | 'D'), modifiers) if modifiers == KeyModifiers::ALT | KeyModifiers::SHIFT => Ok(Self::DuplicateLines),
+                (KeyCode::Up, KeyModifiers::ALT) => Ok(Self::MoveLinesUp),
+                (KeyCode::Down, KeyModifiers::ALT) => Ok(Self::MoveLinesDown),
+                (KeyCode::Char('k'), KeyModifiers::ALT) => Ok(Self::DeleteLines),
+                (KeyCode::Char('j'), KeyModifiers::ALT) => Ok(Self::JoinLines),
+                (KeyCode::Char('s'), KeyModifiers::ALT) => Ok(Self::SortLines),
                 (KeyCode::Up, _) => Ok(Self::Move(Direction::Up)),
                 (KeyCode::Down, _) => Ok(Self::Move(Direction::Down)),
                 (KeyCode::Left, _) => Ok(Self::Move(Direction::Left)),
@@ -46,11 +125,44 @@ impl TryFrom<Event> for EditorCommand {
                 (KeyCode::Backspace, _) => Ok(Self::Backspace),
                 (KeyCode::Delete, _) => Ok(Self::Delete),
                 (KeyCode::Tab, _) => Ok(Self::Insert('\t')),
+                (KeyCode::BackTab, _) => Ok(Self::Dedent),
                 (KeyCode::Enter, _) => Ok(Self::Enter),
                 (KeyCode::Char('s'), KeyModifiers::CONTROL) => Ok(Self::Save),
+                (KeyCode::Char('g'), KeyModifiers::CONTROL) => Ok(Self::GotoLine),
+                (KeyCode::Char('e'), KeyModifiers::CONTROL) => Ok(Self::ScrollLines(1)),
+                (KeyCode::Char('y'), KeyModifiers::CONTROL) => Ok(Self::ScrollLines(-1)),
+                (KeyCode::Char('d'), KeyModifiers::CONTROL) => Ok(Self::AddNextOccurrence),
+                (KeyCode::Char('l'), KeyModifiers::CONTROL) => Ok(Self::Recenter),
+                (KeyCode::Char('z'), KeyModifiers::CONTROL) => Ok(Self::Undo),
+                (KeyCode::Char('r'), KeyModifiers::CONTROL) => Ok(Self::Redo),
+                (KeyCode::Char('x'), KeyModifiers::CONTROL) => Ok(Self::KillRegion),
+                (KeyCode::Char('c'), KeyModifiers::CONTROL) => Ok(Self::CopyRegion),
+                (KeyCode::Char('v'), KeyModifiers::CONTROL) => Ok(Self::Yank),
+                (KeyCode::Char('r'), KeyModifiers::ALT) => Ok(Self::ToggleBlockSelection),
+                (KeyCode::Char(' '), KeyModifiers::CONTROL) => Ok(Self::Complete),
+                // Terminals send Ctrl+] as Ctrl+5
+                (KeyCode::Char(']' | '5'), KeyModifiers::CONTROL) => Ok(Self::JumpToBracket),
+                // and Ctrl+/ as Ctrl+7
+                (KeyCode::Char('/' | '7'), KeyModifiers::CONTROL) | (KeyCode::Char(';'), KeyModifiers::ALT) => Ok(Self::ToggleComment),
+                (KeyCode::Char('n'), KeyModifiers::ALT) => Ok(Self::ToggleLineNumbers),
+                (KeyCode::Char('z'), KeyModifiers::ALT) => Ok(Self::ToggleWrap),
+                (KeyCode::Char('i'), KeyModifiers::ALT) => Ok(Self::ToggleWhitespace),
+                (KeyCode::Char('m'), KeyModifiers::ALT) => Ok(Self::ShowMessages),
+                (KeyCode::Esc, _) => Ok(Self::Dismiss),
                 (KeyCode::Char(character), KeyModifiers::NONE | KeyModifiers::SHIFT) => Ok(Self::Insert(character)),
                 _ => Err(format!("Key Code not supported: {code:?}")),
             },
+            Event::Mouse(MouseEvent{kind, column, row, modifiers}) => {
+                let position = Position{row: row as usize, column: column as usize};
+                match kind {
+                    MouseEventKind::Down(MouseButton::Left) if modifiers.contains(KeyModifiers::CONTROL) => Ok(Self::Mouse(MouseAction::AddCursor, position)),
+                    MouseEventKind::Down(MouseButton::Left) => Ok(Self::Mouse(MouseAction::Click, position)),
+                    MouseEventKind::Drag(MouseButton::Left) => Ok(Self::Mouse(MouseAction::Drag, position)),
+                    MouseEventKind::ScrollUp => Ok(Self::Scroll(-3)),
+                    MouseEventKind::ScrollDown => Ok(Self::Scroll(3)),
+                    _ => Err(format!("Mouse event not supported: {kind:?}")),
+                }
+            },
             Event::Resize(num_columns, num_rows) => Ok(Self::Resize(Size{num_rows: num_rows as usize, num_columns: num_columns as usize})),
             _ => Err(format!("Event not supported: {event:?}")),
         }
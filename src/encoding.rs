@@ -0,0 +1,46 @@
+use std::io::{Error, ErrorKind};
+
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Latin1,
+}
+
+impl Encoding {
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Some(Self::Utf8),
+            "latin-1" | "latin1" | "iso-8859-1" | "iso8859-1" => Some(Self::Latin1),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Latin1 => "Latin-1",
+        }
+    }
+
+    pub fn decode(self, bytes: Vec<u8>) -> Result<String, Error> {
+        match self {
+            Self::Utf8 => String::from_utf8(bytes).map_err(|err| Error::new(ErrorKind::InvalidData, err)),
+            Self::Latin1 => Ok(bytes.into_iter().map(char::from).collect()),
+        }
+    }
+
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Utf8 => Ok(text.as_bytes().to_vec()),
+            Self::Latin1 => text
+                .chars()
+                .map(|character| u8::try_from(character).map_err(|_| {
+                    Error::new(ErrorKind::InvalidData, format!("'{character}' cannot be written as latin-1"))
+                }))
+                .collect(),
+        }
+    }
+
+}
\ No newline at end of file
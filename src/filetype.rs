@@ -0,0 +1,107 @@
+use std::path::Path;
+
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub enum FileType {
+    #[default]
+    PlainText,
+    Rust,
+    C,
+    Cpp,
+    Python,
+    JavaScript,
+    TypeScript,
+    Go,
+    Java,
+    Shell,
+    Toml,
+    Yaml,
+    Json,
+    Markdown,
+    Html,
+    Css,
+    Lua,
+    Sql,
+    Makefile,
+}
+
+impl FileType {
+
+    pub fn from_path(path: &str) -> Self {
+        let path = Path::new(path);
+        if path.file_name().is_some_and(|name| name == "Makefile" || name == "makefile") {
+            return Self::Makefile;
+        }
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+        match extension.to_ascii_lowercase().as_str() {
+            "rs" => Self::Rust,
+            "c" | "h" => Self::C,
+            "cpp" | "cc" | "cxx" | "hpp" | "hh" => Self::Cpp,
+            "py" => Self::Python,
+            "js" | "mjs" | "cjs" | "jsx" => Self::JavaScript,
+            "ts" | "tsx" => Self::TypeScript,
+            "go" => Self::Go,
+            "java" => Self::Java,
+            "sh" | "bash" | "zsh" => Self::Shell,
+            "toml" => Self::Toml,
+            "yaml" | "yml" => Self::Yaml,
+            "json" => Self::Json,
+            "md" | "markdown" => Self::Markdown,
+            "html" | "htm" => Self::Html,
+            "css" => Self::Css,
+            "lua" => Self::Lua,
+            "sql" => Self::Sql,
+            _ => Self::PlainText,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PlainText => "Plain Text",
+            Self::Rust => "Rust",
+            Self::C => "C",
+            Self::Cpp => "C++",
+            Self::Python => "Python",
+            Self::JavaScript => "JavaScript",
+            Self::TypeScript => "TypeScript",
+            Self::Go => "Go",
+            Self::Java => "Java",
+            Self::Shell => "Shell",
+            Self::Toml => "TOML",
+            Self::Yaml => "YAML",
+            Self::Json => "JSON",
+            Self::Markdown => "Markdown",
+            Self::Html => "HTML",
+            Self::Css => "CSS",
+            Self::Lua => "Lua",
+            Self::Sql => "SQL",
+            Self::Makefile => "Makefile",
+        }
+    }
+
+    // Ending a line with one of these indents the next one
+    pub fn block_openers(self) -> &'static [char] {
+        match self {
+            Self::Rust | Self::C | Self::Cpp | Self::JavaScript | Self::TypeScript | Self::Go | Self::Java | Self::Json | Self::Css => &['{', '[', '('],
+            Self::Python | Self::Yaml => &[':'],
+            _ => &[],
+        }
+    }
+
+    // Typing one of these on a blank line takes away a level of indentation
+    pub fn block_closers(self) -> &'static [char] {
+        match self {
+            Self::Rust | Self::C | Self::Cpp | Self::JavaScript | Self::TypeScript | Self::Go | Self::Java | Self::Json | Self::Css => &['}', ']', ')'],
+            _ => &[],
+        }
+    }
+
+    pub fn line_comment(self) -> Option<&'static str> {
+        match self {
+            Self::Rust | Self::C | Self::Cpp | Self::JavaScript | Self::TypeScript | Self::Go | Self::Java => Some("//"),
+            Self::Python | Self::Shell | Self::Toml | Self::Yaml | Self::Makefile => Some("#"),
+            Self::Lua | Self::Sql => Some("--"),
+            _ => None,
+        }
+    }
+
+}
\ No newline at end of file
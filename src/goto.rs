@@ -0,0 +1,106 @@
+use crate::editor::Location;
+use std::{path::Path, str::FromStr};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GotoTarget {
+    Line{line: usize, column: Option<usize>},
+    Relative(isize),
+    Percent(usize),
+}
+
+impl FromStr for GotoTarget {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let invalid = || format!("Invalid position: {input}");
+        if let Some(percent) = input.strip_suffix('%') {
+            return percent.parse().map(Self::Percent).map_err(|_| invalid());
+        }
+        if input.starts_with('+') || input.starts_with('-') {
+            return input.parse().map(Self::Relative).map_err(|_| invalid());
+        }
+        let (line, column) = match input.split_once(':') {
+            Some((line, column)) => (line, Some(column.parse().map_err(|_| invalid())?)),
+            None => (input, None),
+        };
+        let line = line.parse().map_err(|_| invalid())?;
+        Ok(Self::Line{line, column})
+    }
+}
+
+impl GotoTarget {
+
+    // Lines and columns are 1-based, as shown in the status bar
+    pub fn resolve(self, current: Location, total_lines: usize) -> Location {
+        let last_line = total_lines.saturating_sub(1);
+        match self {
+            Self::Line{line, column} => Location{
+                x: column.map_or(0, |column| column.saturating_sub(1)),
+                y: line.saturating_sub(1).min(last_line),
+            },
+            Self::Relative(offset) => Location{
+                x: 0,
+                y: current.y.saturating_add_signed(offset).min(last_line),
+            },
+            Self::Percent(percent) => Location{
+                x: 0,
+                y: (total_lines * percent.min(100) / 100).min(last_line),
+            },
+        }
+    }
+
+}
+
+// Splits `file.rs:4312:8` into the file path and its position, unless a file by that name exists
+pub fn split_file_position(argument: &str) -> (String, Option<GotoTarget>) {
+    if Path::new(argument).exists() {
+        return (argument.to_string(), None);
+    }
+    let parts: Vec<&str> = argument.rsplitn(3, ':').collect();
+    if let [column, line, path] = parts.as_slice() {
+        if let (Ok(line), Ok(column), false) = (line.parse(), column.parse(), path.is_empty()) {
+            return (path.to_string(), Some(GotoTarget::Line{line, column: Some(column)}));
+        }
+    }
+    if let Some((path, line)) = argument.rsplit_once(':') {
+        if let (Ok(line), false) = (line.parse(), path.is_empty()) {
+            return (path.to_string(), Some(GotoTarget::Line{line, column: None}));
+        }
+    }
+    (argument.to_string(), None)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{split_file_position, GotoTarget};
+    use crate::editor::Location;
+
+    fn resolve(input: &str, current: Location) -> Location {
+        input.parse::<GotoTarget>().unwrap().resolve(current, 100)
+    }
+
+    #[test]
+    fn resolves_absolute_relative_and_percent_targets() {
+        let current = Location{x: 3, y: 9};
+        assert_eq!(resolve("20:5", current), Location{x: 4, y: 19});
+        assert_eq!(resolve("+5", current), Location{x: 0, y: 14});
+        assert_eq!(resolve("-50", current), Location{x: 0, y: 0});
+        assert_eq!(resolve("50%", current), Location{x: 0, y: 50});
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!("abc".parse::<GotoTarget>().is_err());
+    }
+
+    #[test]
+    fn file_arguments_can_carry_a_position() {
+        assert_eq!(split_file_position("notes.txt:12:4"), ("notes.txt".to_string(), Some(GotoTarget::Line{line: 12, column: Some(4)})));
+        assert_eq!(split_file_position("notes.txt:12"), ("notes.txt".to_string(), Some(GotoTarget::Line{line: 12, column: None})));
+        assert_eq!(split_file_position(":12"), (":12".to_string(), None));
+        assert_eq!(split_file_position("notes.txt"), ("notes.txt".to_string(), None));
+    }
+
+}
\ No newline at end of file
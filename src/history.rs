@@ -0,0 +1,142 @@
+use crate::editor::Location;
+
+#[derive(Clone)]
+pub enum Edit {
+    // `new_line` is set when the insert had to append a line past the end of the buffer first
+    Insert{start: Location, end: Location, text: String, new_line: bool},
+    Delete{start: Location, end: Location, text: String},
+}
+
+impl Edit {
+
+    // Where `location` ends up once this edit has been made, so that other cursors stay on the same text
+    pub fn shift(&self, location: Location) -> Location {
+        match *self {
+            Self::Insert{start, end, ..} if location >= start => {
+                if location.y == start.y {
+                    Location{x: end.x + location.x - start.x, y: end.y}
+                }
+                else {
+                    Location{x: location.x, y: location.y + end.y - start.y}
+                }
+            },
+            Self::Delete{start, end, ..} if location > start => {
+                if location <= end {
+                    start
+                }
+                else if location.y == end.y {
+                    Location{x: start.x + location.x - end.x, y: start.y}
+                }
+                else {
+                    Location{x: location.x, y: location.y - (end.y - start.y)}
+                }
+            },
+            _ => location,
+        }
+    }
+
+}
+
+// One undo step, with the cursor positions to return to either side of it
+#[derive(Clone)]
+pub struct Change {
+    pub edits: Vec<Edit>,
+    pub before: Location,
+    pub after: Location,
+}
+
+pub struct History {
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+    pending: Option<Change>,
+    in_change: bool,
+    can_merge: bool,
+    saved: Option<usize>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self{undo: Vec::new(), redo: Vec::new(), pending: None, in_change: false, can_merge: false, saved: Some(0)}
+    }
+}
+
+impl History {
+
+    // Edits made until `end` form one step, which the next change may continue when both allow merging
+    pub fn begin(&mut self, cursor: Location, merge: bool) {
+        if !(merge && self.can_merge) {
+            self.commit();
+        }
+        self.can_merge = merge;
+        self.in_change = true;
+        if self.pending.is_none() {
+            self.pending = Some(Change{edits: Vec::new(), before: cursor, after: cursor});
+        }
+    }
+
+    pub fn end(&mut self, cursor: Location) {
+        self.in_change = false;
+        if let Some(change) = &mut self.pending {
+            change.after = cursor;
+        }
+        if self.pending.as_ref().is_some_and(|change| change.edits.is_empty()) {
+            self.pending = None;
+        }
+    }
+
+    pub fn record(&mut self, edit: Edit) {
+        if let Some(change) = self.pending.as_mut().filter(|_| self.in_change) {
+            change.edits.push(edit);
+            return;
+        }
+        self.commit();
+        let (Edit::Insert{start, end, ..} | Edit::Delete{start, end, ..}) = edit;
+        self.push(Change{edits: vec![edit], before: start, after: end});
+    }
+
+    pub fn edit_count(&self) -> usize {
+        self.pending.as_ref().map_or(0, |change| change.edits.len())
+    }
+
+    // Edits made in the current change after the first `count`
+    pub fn edits_since(&self, count: usize) -> Vec<Edit> {
+        self.pending.as_ref().map(|change| change.edits[count.min(change.edits.len())..].to_vec()).unwrap_or_default()
+    }
+
+    fn commit(&mut self) {
+        if let Some(change) = self.pending.take().filter(|change| !change.edits.is_empty()) {
+            self.push(change);
+        }
+    }
+
+    fn push(&mut self, change: Change) {
+        if self.saved.is_some_and(|saved| saved > self.undo.len()) {
+            self.saved = None;
+        }
+        self.undo.push(change);
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self) -> Option<Change> {
+        self.commit();
+        let change = self.undo.pop()?;
+        self.redo.push(change.clone());
+        Some(change)
+    }
+
+    pub fn redo(&mut self) -> Option<Change> {
+        let change = self.redo.pop()?;
+        self.undo.push(change.clone());
+        Some(change)
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.commit();
+        self.saved = Some(self.undo.len());
+    }
+
+    pub fn is_saved(&self) -> bool {
+        self.pending.is_none() && self.saved == Some(self.undo.len())
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,99 @@
+use crate::editorcommand::{EditorCommand, Direction};
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use std::convert::TryFrom;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Keymap {
+    #[default]
+    Default,
+    Emacs,
+}
+
+impl Keymap {
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "default" => Some(Self::Default),
+            "emacs" => Some(Self::Emacs),
+            _ => None,
+        }
+    }
+
+    // A preset named by the `EDITOR_KEYMAP` variable wins over the config file, unless it is unknown
+    pub fn with_override(self, name: Option<&str>) -> Self {
+        name.and_then(Self::from_name).unwrap_or(self)
+    }
+
+    pub fn translate(self, event: Event) -> Result<EditorCommand, String> {
+        match self {
+            Self::Default => EditorCommand::try_from(event),
+            Self::Emacs => Self::translate_emacs(&event).map_or_else(|| EditorCommand::try_from(event), Ok),
+        }
+    }
+
+    fn translate_emacs(event: &Event) -> Option<EditorCommand> {
+        let Event::Key(KeyEvent{code, modifiers, ..}) = event else {
+            return None;
+        };
+        let command = match (code, *modifiers) {
+            (KeyCode::Char('a'), KeyModifiers::CONTROL) => EditorCommand::Move(Direction::Home),
+            (KeyCode::Char('e'), KeyModifiers::CONTROL) => EditorCommand::Move(Direction::End),
+            (KeyCode::Char('f'), KeyModifiers::CONTROL) => EditorCommand::Move(Direction::Right),
+            (KeyCode::Char('b'), KeyModifiers::CONTROL) => EditorCommand::Move(Direction::Left),
+            (KeyCode::Char('n'), KeyModifiers::CONTROL) => EditorCommand::Move(Direction::Down),
+            (KeyCode::Char('p'), KeyModifiers::CONTROL) => EditorCommand::Move(Direction::Up),
+            (KeyCode::Char('v'), KeyModifiers::CONTROL) => EditorCommand::Move(Direction::PageDown),
+            (KeyCode::Char('v'), KeyModifiers::ALT) => EditorCommand::Move(Direction::PageUp),
+            (KeyCode::Char('f'), KeyModifiers::ALT) => EditorCommand::Move(Direction::WordRight),
+            (KeyCode::Char('b'), KeyModifiers::ALT) => EditorCommand::Move(Direction::WordLeft),
+            (KeyCode::Char('<'), _) if modifiers.contains(KeyModifiers::ALT) => EditorCommand::Move(Direction::BufferStart),
+            (KeyCode::Char('>'), _) if modifiers.contains(KeyModifiers::ALT) => EditorCommand::Move(Direction::BufferEnd),
+            (KeyCode::Char('d'), KeyModifiers::CONTROL) => EditorCommand::Delete,
+            (KeyCode::Char('d'), KeyModifiers::ALT) => EditorCommand::DeleteWordForward,
+            (KeyCode::Char('k'), KeyModifiers::CONTROL) => EditorCommand::KillLine,
+            (KeyCode::Char('w'), KeyModifiers::CONTROL) => EditorCommand::KillRegion,
+            (KeyCode::Char('w'), KeyModifiers::ALT) => EditorCommand::CopyRegion,
+            (KeyCode::Char('y'), KeyModifiers::CONTROL) => EditorCommand::Yank,
+            (KeyCode::Char('/' | '_' | '7'), KeyModifiers::CONTROL) => EditorCommand::Undo,
+            (KeyCode::Char('y'), KeyModifiers::ALT) => EditorCommand::YankPop,
+            (KeyCode::Char(' ' | '@'), KeyModifiers::CONTROL) => EditorCommand::SetMark,
+            (KeyCode::Char('g'), KeyModifiers::CONTROL) => EditorCommand::ClearMark,
+            (KeyCode::Char('g'), KeyModifiers::ALT) => EditorCommand::GotoLine,
+            // Ctrl+Space sets the mark here, so completion moves to Emacs's own expansion key
+            (KeyCode::Char('/'), KeyModifiers::ALT) => EditorCommand::Complete,
+            _ => return None,
+        };
+        Some(command)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Keymap;
+    use crate::editorcommand::{Direction, EditorCommand};
+    use crate::testing::{ctrl, key};
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn emacs_keys_fall_back_to_the_default_bindings() {
+        let alt = |character| key(KeyCode::Char(character), KeyModifiers::ALT);
+        assert!(matches!(Keymap::Emacs.translate(ctrl('a')), Ok(EditorCommand::Move(Direction::Home))));
+        assert!(matches!(Keymap::Emacs.translate(ctrl('k')), Ok(EditorCommand::KillLine)));
+        assert!(matches!(Keymap::Emacs.translate(alt('y')), Ok(EditorCommand::YankPop)));
+        assert!(matches!(Keymap::Emacs.translate(ctrl(' ')), Ok(EditorCommand::SetMark)));
+        assert!(matches!(Keymap::Emacs.translate(alt('/')), Ok(EditorCommand::Complete)));
+        assert!(matches!(Keymap::Emacs.translate(ctrl('s')), Ok(EditorCommand::Save)));
+        assert!(Keymap::Default.translate(ctrl('k')).is_err());
+    }
+
+    #[test]
+    fn environment_preset_overrides_the_config() {
+        assert_eq!(Keymap::Default.with_override(Some("Emacs")), Keymap::Emacs);
+        assert_eq!(Keymap::Emacs.with_override(Some("vi")), Keymap::Emacs);
+        assert_eq!(Keymap::Emacs.with_override(None), Keymap::Emacs);
+    }
+
+}
\ No newline at end of file
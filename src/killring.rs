@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+
+const MAX_ENTRIES: usize = 60;
+
+// Block entries hold one row of a rectangle per line and are yanked back as a rectangle
+struct Entry {
+    text: String,
+    is_block: bool,
+}
+
+#[derive(Default)]
+pub struct KillRing {
+    entries: VecDeque<Entry>,
+    yank_index: usize,
+}
+
+impl KillRing {
+
+    pub fn push(&mut self, text: String) {
+        self.push_entry(Entry{text, is_block: false});
+    }
+
+    pub fn push_block(&mut self, text: String) {
+        self.push_entry(Entry{text, is_block: true});
+    }
+
+    fn push_entry(&mut self, entry: Entry) {
+        if entry.text.is_empty() {
+            return;
+        }
+        self.entries.push_front(entry);
+        self.entries.truncate(MAX_ENTRIES);
+        self.yank_index = 0;
+    }
+
+    pub fn append(&mut self, text: &str) {
+        match self.entries.front_mut() {
+            Some(entry) if !entry.is_block => {
+                entry.text.push_str(text);
+                self.yank_index = 0;
+            },
+            _ => self.push(text.to_string()),
+        }
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.entries.get(self.yank_index).map(|entry| entry.text.as_str())
+    }
+
+    pub fn is_block(&self) -> bool {
+        self.entries.get(self.yank_index).is_some_and(|entry| entry.is_block)
+    }
+
+    pub fn rotate(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        self.yank_index = (self.yank_index + 1) % self.entries.len();
+        self.current()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::KillRing;
+
+    #[test]
+    fn appends_join_the_latest_kill() {
+        let mut kill_ring = KillRing::default();
+        kill_ring.push(String::new());
+        assert_eq!(kill_ring.current(), None);
+        kill_ring.append("one");
+        kill_ring.append("\n");
+        assert_eq!(kill_ring.current(), Some("one\n"));
+        kill_ring.push("two".to_string());
+        assert_eq!(kill_ring.current(), Some("two"));
+    }
+
+    #[test]
+    fn rotating_cycles_through_older_kills() {
+        let mut kill_ring = KillRing::default();
+        assert_eq!(kill_ring.rotate(), None);
+        for text in ["a", "b", "c"] {
+            kill_ring.push(text.to_string());
+        }
+        assert_eq!(kill_ring.rotate(), Some("b"));
+        assert_eq!(kill_ring.rotate(), Some("a"));
+        assert_eq!(kill_ring.rotate(), Some("c"));
+        kill_ring.rotate();
+        kill_ring.push("d".to_string());
+        assert_eq!(kill_ring.current(), Some("d"));
+    }
+
+}
\ No newline at end of file
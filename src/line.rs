@@ -1,5 +1,48 @@
-use std::{cmp::min, fmt, fmt::Display, ops::Range};
+use std::{fmt, fmt::Display, ops::Range};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum WordClass {
+    Whitespace,
+    Punctuation,
+    Word,
+}
+
+impl WordClass {
+
+    fn of(segment: &str) -> Self {
+        if segment.chars().all(char::is_whitespace) {
+            Self::Whitespace
+        }
+        else if segment.chars().any(|character| character.is_alphanumeric() || character == '_') {
+            Self::Word
+        }
+        else {
+            Self::Punctuation
+        }
+    }
+
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum GlyphKind {
+    Text,
+    Tab,
+    Space,
+    NonBreakingSpace,
+    Control,
+}
+
+// How a single grapheme is laid out on screen
+pub struct Glyph {
+    pub column: usize,
+    pub width: usize,
+    pub text: String,
+    pub kind: GlyphKind,
+}
+
+#[derive(Default)]
 pub struct Line {
     pub string: String
 }
@@ -12,14 +55,211 @@ impl Line {
         }
     }
 
+    // Ranges are measured in grapheme clusters, not bytes
     pub fn get(&self, range: Range<usize>) -> String {
-        let start = range.start;
-        let end = min(range.end, self.string.len());
-        self.string.get(start..end).unwrap_or_default().to_string()
+        let start = self.byte_offset(range.start);
+        let end = self.byte_offset(range.end);
+        self.string.get(start..end.max(start)).unwrap_or_default().to_string()
     }
 
     pub fn len(&self) -> usize {
-        self.string.len()
+        self.string.graphemes(true).count()
+    }
+
+    pub fn previous_word_start(&self, x: usize) -> usize {
+        self.word_segments()
+            .into_iter()
+            .rev()
+            .find(|(range, class)| range.start < x && *class != WordClass::Whitespace)
+            .map_or(0, |(range, _)| range.start)
+    }
+
+    pub fn next_word_end(&self, x: usize) -> usize {
+        self.word_segments()
+            .into_iter()
+            .find(|(range, class)| range.end > x && *class != WordClass::Whitespace)
+            .map_or_else(|| self.len(), |(range, _)| range.end)
+    }
+
+    // The word, run of punctuation or run of whitespace under `x`
+    pub fn word_at(&self, x: usize) -> Range<usize> {
+        let x = x.min(self.len().saturating_sub(1));
+        self.word_segments()
+            .into_iter()
+            .map(|(range, _)| range)
+            .find(|range| range.contains(&x))
+            .unwrap_or(x..x)
+    }
+
+    // The word that ends exactly at `x`, if the cursor there is just after one
+    pub fn word_ending_at(&self, x: usize) -> Option<Range<usize>> {
+        self.word_segments()
+            .into_iter()
+            .find(|(range, class)| range.end == x && *class == WordClass::Word)
+            .map(|(range, _)| range)
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.string.split_word_bounds().filter(|word| WordClass::of(word) == WordClass::Word)
+    }
+
+    pub fn glyphs(&self, tab_width: usize) -> Vec<Glyph> {
+        let tab_width = tab_width.max(1);
+        let mut column = 0;
+        self.string
+            .graphemes(true)
+            .map(|grapheme| {
+                let (text, kind) = match grapheme {
+                    "\t" => (" ".repeat(tab_width - column % tab_width), GlyphKind::Tab),
+                    " " => (grapheme.to_string(), GlyphKind::Space),
+                    "\u{a0}" => (" ".to_string(), GlyphKind::NonBreakingSpace),
+                    _ if grapheme.chars().any(char::is_control) => (Self::escape_control(grapheme), GlyphKind::Control),
+                    _ => (grapheme.to_string(), GlyphKind::Text),
+                };
+                let width = text.width();
+                let glyph = Glyph{column, width, text, kind};
+                column += width;
+                glyph
+            })
+            .collect()
+    }
+
+    // Control characters use caret notation (e.g. `^[` for escape) so they never reach the terminal raw
+    fn escape_control(grapheme: &str) -> String {
+        grapheme
+            .chars()
+            .map(|character| match u8::try_from(character) {
+                Ok(byte @ 0..=0x1f) => format!("^{}", char::from(byte + 0x40)),
+                Ok(0x7f) => "^?".to_string(),
+                _ if character.is_control() => format!("<{:02x}>", u32::from(character)),
+                _ => character.to_string(),
+            })
+            .collect()
+    }
+
+    pub fn trailing_whitespace_column(&self, tab_width: usize) -> usize {
+        self.glyphs(tab_width)
+            .iter()
+            .rev()
+            .find(|glyph| !matches!(glyph.kind, GlyphKind::Space | GlyphKind::Tab))
+            .map_or(0, |glyph| glyph.column + glyph.width)
+    }
+
+    pub fn column_of(&self, x: usize, tab_width: usize) -> usize {
+        let glyphs = self.glyphs(tab_width);
+        match glyphs.get(x) {
+            Some(glyph) => glyph.column,
+            None => glyphs.last().map_or(0, |glyph| glyph.column + glyph.width),
+        }
+    }
+
+    pub fn index_at_column(&self, column: usize, tab_width: usize) -> usize {
+        let glyphs = self.glyphs(tab_width);
+        glyphs
+            .iter()
+            .position(|glyph| glyph.column + glyph.width > column)
+            .unwrap_or(glyphs.len())
+    }
+
+    // The graphemes that overlap the display columns in `columns`, so a tab or wide character cut by either edge is included
+    pub fn range_at_columns(&self, columns: Range<usize>, tab_width: usize) -> Range<usize> {
+        let glyphs = self.glyphs(tab_width);
+        let start = glyphs.iter().position(|glyph| glyph.column + glyph.width > columns.start).unwrap_or(glyphs.len());
+        if columns.is_empty() {
+            return start..start;
+        }
+        let end = glyphs.iter().position(|glyph| glyph.column >= columns.end).unwrap_or(glyphs.len());
+        start..end.max(start)
+    }
+
+    // Clips the glyphs to the display columns in `columns`, replacing glyphs cut off at either edge with spaces
+    pub fn render(&self, columns: Range<usize>, tab_width: usize) -> Vec<Glyph> {
+        let mut result = Vec::new();
+        for glyph in self.glyphs(tab_width) {
+            let glyph_end = glyph.column + glyph.width;
+            if glyph_end <= columns.start || glyph.width == 0 && glyph.column < columns.start {
+                continue;
+            }
+            if glyph.column >= columns.end {
+                break;
+            }
+            if glyph.column < columns.start || glyph_end > columns.end {
+                let column = glyph.column.max(columns.start);
+                let width = glyph_end.min(columns.end) - column;
+                result.push(Glyph{column, width, text: " ".repeat(width), kind: GlyphKind::Text});
+            }
+            else {
+                result.push(glyph);
+            }
+        }
+        result
+    }
+
+    // Splits the line into rows at most `width` columns wide, preferring to break after whitespace
+    pub fn wrap(&self, width: usize, at_words: bool, tab_width: usize) -> Vec<Range<usize>> {
+        let width = width.max(1);
+        let glyphs = self.glyphs(tab_width);
+        let is_whitespace: Vec<bool> = glyphs.iter().map(|glyph| glyph.text.chars().all(char::is_whitespace)).collect();
+        let mut rows = Vec::new();
+        let mut start = 0;
+        let mut row_width = 0;
+        for (index, glyph) in glyphs.iter().enumerate() {
+            if row_width + glyph.width > width && index > start {
+                let end = if at_words {
+                    (start + 1..=index).rev().find(|&end| is_whitespace[end - 1] && !is_whitespace[end]).unwrap_or(index)
+                }
+                else {
+                    index
+                };
+                rows.push(start..end);
+                row_width = glyphs[end..index].iter().map(|glyph| glyph.width).sum();
+                start = end;
+            }
+            row_width += glyph.width;
+        }
+        rows.push(start..glyphs.len());
+        if !glyphs.is_empty() && row_width >= width {
+            rows.push(glyphs.len()..glyphs.len());
+        }
+        rows
+    }
+
+    fn word_segments(&self) -> Vec<(Range<usize>, WordClass)> {
+        let mut segments: Vec<(Range<usize>, WordClass)> = Vec::new();
+        let mut index = 0;
+        for word in self.string.split_word_bounds() {
+            let mut is_word_start = true;
+            for grapheme in word.graphemes(true) {
+                let class = WordClass::of(grapheme);
+                match segments.last_mut() {
+                    Some((range, last_class)) if *last_class == class && !(is_word_start && class == WordClass::Word) => range.end += 1,
+                    _ => segments.push((index..index + 1, class)),
+                }
+                is_word_start = false;
+                index += 1;
+            }
+        }
+        segments
+    }
+
+    // The grapheme index of the first match of `needle` at or after `from`, skipping matches that split a grapheme
+    pub fn find(&self, needle: &str, from: usize) -> Option<usize> {
+        let boundaries: Vec<usize> = self.string.grapheme_indices(true).map(|(offset, _)| offset).chain(std::iter::once(self.string.len())).collect();
+        let start = *boundaries.get(from)?;
+        self.string[start..]
+            .match_indices(needle)
+            .map(|(offset, _)| start + offset)
+            .find_map(|offset| {
+                let index = boundaries.binary_search(&offset).ok()?;
+                boundaries.binary_search(&(offset + needle.len())).ok().map(|_| index)
+            })
+    }
+
+    fn byte_offset(&self, grapheme_index: usize) -> usize {
+        self.string
+            .grapheme_indices(true)
+            .nth(grapheme_index)
+            .map_or(self.string.len(), |(offset, _)| offset)
     }
 
 }
@@ -28,4 +268,54 @@ impl Display for Line {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         write!(formatter, "{}", self.string)
     }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{GlyphKind, Line};
+
+    #[test]
+    fn tabs_expand_to_the_next_stop() {
+        let line = Line::from("a\tb");
+        assert_eq!(line.column_of(2, 4), 4);
+        assert_eq!(line.index_at_column(2, 4), 1);
+        assert_eq!(line.column_of(2, 8), 8);
+    }
+
+    #[test]
+    fn column_ranges_include_glyphs_cut_at_the_edges() {
+        let line = Line::from("a\tb漢c");
+        assert_eq!(line.range_at_columns(1..4, 4), 1..2);
+        assert_eq!(line.range_at_columns(2..5, 4), 1..3);
+        assert_eq!(line.range_at_columns(5..6, 4), 3..4);
+        assert_eq!(line.range_at_columns(6..6, 4), 3..3);
+        assert_eq!(line.range_at_columns(20..30, 4), 5..5);
+    }
+
+    #[test]
+    fn control_characters_use_caret_notation() {
+        let glyphs = Line::from("a\u{1b}\u{7f}").glyphs(4);
+        assert_eq!(glyphs[1].text, "^[");
+        assert_eq!(glyphs[2].text, "^?");
+        assert!(glyphs[1].kind == GlyphKind::Control);
+        assert_eq!(glyphs[2].column, 3);
+    }
+
+    #[test]
+    fn word_movement_skips_punctuation_runs() {
+        let line = Line::from("let x = foo.bar();");
+        assert_eq!(line.next_word_end(0), 3);
+        assert_eq!(line.next_word_end(8), 11);
+        assert_eq!(line.previous_word_start(15), 12);
+    }
+
+    #[test]
+    fn word_wrap_breaks_between_words() {
+        let line = Line::from("the quick brown fox");
+        assert_eq!(line.wrap(10, true, 4), vec![0..10, 10..19]);
+        assert_eq!(line.wrap(10, false, 4), vec![0..10, 10..19]);
+        assert_eq!(Line::from("abcdefgh").wrap(4, true, 4), vec![0..4, 4..8, 8..8]);
+    }
+
 }
\ No newline at end of file
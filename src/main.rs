@@ -1,16 +1,60 @@
 #![warn(clippy::all, clippy::pedantic, clippy::print_stdout)]
 
-pub mod editor;
-pub mod editorcommand;
-pub mod terminal;
-pub mod view;
-pub mod statusbar;
-pub mod messagebar;
-pub mod buffer;
-pub mod line;
-pub mod uicomponent;
+mod editor;
+mod editorcommand;
+mod terminal;
+mod view;
+mod statusbar;
+mod messagebar;
+mod buffer;
+mod line;
+mod uicomponent;
+mod screen;
+mod backend;
+#[cfg(test)]
+mod testing;
+mod keymap;
+mod killring;
+mod commandbar;
+mod goto;
+mod cli;
+mod config;
+mod encoding;
+mod theme;
+mod filetype;
+mod history;
+mod completion;
 use editor::Editor;
+use cli::Action;
+use config::Config;
+use std::{env, io::{stdout, Write}, process};
 
-fn main() { 
-    Editor::new().unwrap().run();
+fn main() {
+    let arguments = match cli::parse(env::args().skip(1)) {
+        Ok(Action::Run(arguments)) => arguments,
+        Ok(Action::Help) => {
+            let _ = writeln!(stdout(), "{}", cli::usage());
+            return;
+        },
+        Ok(Action::Version) => {
+            let _ = writeln!(stdout(), "{}", cli::version());
+            return;
+        },
+        Err(err) => {
+            eprintln!("{err}\n\n{}", cli::usage());
+            process::exit(2);
+        },
+    };
+    let mut config = Config::load(arguments.config_path.as_deref()).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(1);
+    });
+    config.keymap = config.keymap.with_override(env::var("EDITOR_KEYMAP").ok().as_deref());
+    match Editor::new(arguments, &config) {
+        Ok(mut editor) => editor.run(),
+        Err(err) => {
+            eprintln!("Could not start the editor: {err}");
+            process::exit(1);
+        },
+    }
 }
\ No newline at end of file
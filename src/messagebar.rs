@@ -1,26 +1,86 @@
-use crate::terminal::Terminal;
+use crate::screen::Screen;
+use crate::theme::{Face, Theme};
 use crate::terminal::{Size, Position};
 use crate::uicomponent::UIComponent;
-use std::io::Error;
+use std::{collections::VecDeque, time::{Duration, Instant}};
+
+const MAX_HISTORY: usize = 200;
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+
+}
+
+struct Message {
+    text: String,
+    severity: Severity,
+    shown_at: Instant,
+}
 
 #[derive(Default)]
 pub struct MessageBar {
     needs_redraw: bool,
-    message: String,
+    current: Option<Message>,
+    history: VecDeque<(Severity, String)>,
+    timeout: Option<Duration>,
     size: Size,
 }
 
 impl MessageBar {
 
-    pub fn set_message(&mut self, message: String) {
-        self.message = message;
+    // `None` keeps every message on screen until the next one replaces it
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_message(&mut self, severity: Severity, message: String) {
+        if self.history.len() == MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back((severity, message.clone()));
+        self.current = Some(Message{text: message, severity, shown_at: Instant::now()});
         self.set_redraw(true);
     }
 
+    pub fn time_left(&self, now: Instant) -> Option<Duration> {
+        let timeout = self.timeout?;
+        let message = self.current.as_ref()?;
+        Some(timeout.saturating_sub(now.saturating_duration_since(message.shown_at)))
+    }
+
+    pub fn expire(&mut self, now: Instant) {
+        if self.time_left(now).is_some_and(|time_left| time_left.is_zero()) {
+            self.current = None;
+            self.set_redraw(true);
+        }
+    }
+
+    pub fn history(&self) -> String {
+        self.history
+            .iter()
+            .map(|(severity, message)| format!("{}: {message}", severity.name()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
 }
 
 impl UIComponent for MessageBar {
-    
+
     fn set_redraw(&mut self, needs_redraw: bool) {
         self.needs_redraw = needs_redraw;
     }
@@ -33,10 +93,56 @@ impl UIComponent for MessageBar {
         self.size = size;
     }
 
-    fn draw(&mut self, start_row: usize) -> Result<(), Error> {
-        Terminal::move_cursor_to(Position{row: start_row, column: 0})?;
-        Terminal::print(&self.message)?;
-        Ok(())
+    fn draw(&mut self, screen: &mut Screen, theme: &Theme, start_row: usize) {
+        screen.move_cursor_to(Position{row: start_row, column: 0});
+        screen.set_style(theme.style(Face::MessageBar));
+        screen.clear_line();
+        if let Some(Message{text, severity, ..}) = &self.current {
+            let face = match severity {
+                Severity::Info => Face::MessageBar,
+                Severity::Warning => Face::MessageWarning,
+                Severity::Error => Face::MessageError,
+            };
+            screen.set_style(theme.style(Face::MessageBar).patch(theme.style(face)));
+            screen.print(text);
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{MessageBar, Severity};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn messages_expire_after_the_timeout() {
+        let mut message_bar = MessageBar::default();
+        message_bar.set_timeout(Some(Duration::from_secs(5)));
+        message_bar.set_message(Severity::Info, "Saved".to_string());
+        let now = Instant::now();
+        assert!(message_bar.time_left(now).is_some_and(|time_left| time_left <= Duration::from_secs(5)));
+        message_bar.expire(now + Duration::from_secs(6));
+        assert!(message_bar.current.is_none());
+        assert!(message_bar.time_left(now).is_none());
+    }
+
+    #[test]
+    fn messages_stay_without_a_timeout() {
+        let mut message_bar = MessageBar::default();
+        message_bar.set_message(Severity::Error, "Could not save".to_string());
+        message_bar.expire(Instant::now() + Duration::from_secs(1000));
+        assert!(message_bar.current.is_some());
+    }
+
+    #[test]
+    fn history_keeps_every_message_in_order() {
+        let mut message_bar = MessageBar::default();
+        message_bar.set_message(Severity::Info, "one".to_string());
+        message_bar.set_message(Severity::Warning, "two".to_string());
+        message_bar.set_message(Severity::Error, "three".to_string());
+        assert_eq!(message_bar.history(), "info: one\nwarning: two\nerror: three");
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,202 @@
+use crate::terminal::{Size, Position};
+use crossterm::style::Color;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub struct Style {
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub reverse: bool,
+}
+
+impl Style {
+
+    // Layers `other` on top, keeping whatever it leaves unset
+    pub fn patch(self, other: Style) -> Style {
+        Style{
+            foreground: other.foreground.or(self.foreground),
+            background: other.background.or(self.background),
+            bold: self.bold || other.bold,
+            dim: self.dim || other.dim,
+            reverse: self.reverse || other.reverse,
+        }
+    }
+
+}
+
+// A wide character occupies its first cell; the cells it covers after that have a width of 0
+#[derive(Clone, Eq, PartialEq)]
+pub struct Cell {
+    pub symbol: String,
+    pub width: usize,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self{symbol: " ".to_string(), width: 1, style: Style::default()}
+    }
+}
+
+#[derive(Clone)]
+pub struct Screen {
+    size: Size,
+    cells: Vec<Cell>,
+    cursor: Position,
+    style: Style,
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self::new(Size::default())
+    }
+}
+
+impl Screen {
+
+    pub fn new(size: Size) -> Self {
+        Self{size, cells: vec![Cell::default(); size.num_rows * size.num_columns], cursor: Position::default(), style: Style::default()}
+    }
+
+    pub fn cell(&self, position: Position) -> Option<&Cell> {
+        if position.row < self.size.num_rows && position.column < self.size.num_columns {
+            self.cells.get(position.row * self.size.num_columns + position.column)
+        }
+        else {
+            None
+        }
+    }
+
+    // The cells that differ from `previous`, skipping the trailing halves of wide characters
+    pub fn changes<'a>(&'a self, previous: &'a Screen) -> impl Iterator<Item = (Position, &'a Cell)> + 'a {
+        let num_columns = self.size.num_columns;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(index, cell)| (Position{row: index / num_columns, column: index % num_columns}, cell))
+            .filter(move |(position, cell)| cell.width > 0 && previous.cell(*position) != Some(*cell))
+    }
+
+    pub fn move_cursor_to(&mut self, position: Position) {
+        self.cursor = position;
+    }
+
+    pub fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    pub fn clear_line(&mut self) {
+        if self.cursor.row < self.size.num_rows {
+            let start = self.cursor.row * self.size.num_columns;
+            self.cells[start..start + self.size.num_columns].fill(Cell{style: self.style, ..Cell::default()});
+        }
+    }
+
+    // Restyles a cell that has already been drawn
+    pub fn patch_style(&mut self, position: Position, style: Style) {
+        if position.row < self.size.num_rows && position.column < self.size.num_columns {
+            let cell = &mut self.cells[position.row * self.size.num_columns + position.column];
+            cell.style = cell.style.patch(style);
+        }
+    }
+
+    pub fn print(&mut self, string: &str) {
+        let Position{row, mut column} = self.cursor;
+        if row >= self.size.num_rows {
+            return;
+        }
+        let row_start = row * self.size.num_columns;
+        for grapheme in string.graphemes(true) {
+            let width = grapheme.width();
+            if width == 0 {
+                // Zero-width graphemes attach to whatever was printed before them
+                if let Some(cell) = column.checked_sub(1).and_then(|previous| self.cells.get_mut(row_start + previous)) {
+                    cell.symbol.push_str(grapheme);
+                }
+                continue;
+            }
+            if column + width > self.size.num_columns {
+                break;
+            }
+            for offset in 0..width {
+                self.clear_cell(row_start, column + offset);
+            }
+            let index = row_start + column;
+            self.cells[index] = Cell{symbol: grapheme.to_string(), width, style: self.style};
+            for offset in 1..width {
+                self.cells[index + offset] = Cell{symbol: String::new(), width: 0, style: self.style};
+            }
+            column += width;
+        }
+        self.cursor.column = column;
+    }
+
+    // Blanks out any wide character overlapping the cell, so none is left half overwritten
+    fn clear_cell(&mut self, row_start: usize, column: usize) {
+        let mut start = row_start + column;
+        while start > row_start && self.cells[start].width == 0 {
+            start -= 1;
+        }
+        let end = start + self.cells[start].width.max(1);
+        if end - start > 1 || start != row_start + column {
+            self.cells[start..end].fill(Cell::default());
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Screen, Style};
+    use crate::terminal::{Size, Position};
+
+    fn row_text(screen: &Screen, row: usize) -> String {
+        (0..screen.size.num_columns)
+            .filter_map(|column| screen.cell(Position{row, column}))
+            .map(|cell| cell.symbol.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn wide_characters_cover_two_cells() {
+        let mut screen = Screen::new(Size{num_rows: 1, num_columns: 6});
+        screen.print("a日b");
+        assert_eq!(row_text(&screen, 0), "a日b  ");
+        assert_eq!(screen.cell(Position{row: 0, column: 2}).unwrap().width, 0);
+    }
+
+    #[test]
+    fn overwriting_half_a_wide_character_blanks_the_rest() {
+        let mut screen = Screen::new(Size{num_rows: 1, num_columns: 4});
+        screen.print("日本");
+        screen.move_cursor_to(Position{row: 0, column: 1});
+        screen.print("x");
+        assert_eq!(row_text(&screen, 0), " x本");
+    }
+
+    #[test]
+    fn only_changed_cells_are_reported() {
+        let mut previous = Screen::new(Size{num_rows: 2, num_columns: 4});
+        previous.print("abcd");
+        let mut current = previous.clone();
+        current.move_cursor_to(Position{row: 0, column: 2});
+        current.set_style(Style{reverse: true, ..Style::default()});
+        current.print("c");
+        current.move_cursor_to(Position{row: 1, column: 0});
+        current.print("z");
+        let changes: Vec<_> = current.changes(&previous).map(|(position, cell)| (position.row, position.column, cell.symbol.clone())).collect();
+        assert_eq!(changes, vec![(0, 2, "c".to_string()), (1, 0, "z".to_string())]);
+    }
+
+    #[test]
+    fn text_past_the_edge_is_clipped() {
+        let mut screen = Screen::new(Size{num_rows: 1, num_columns: 3});
+        screen.print("ab日");
+        assert_eq!(row_text(&screen, 0), "ab ");
+    }
+
+}
\ No newline at end of file
@@ -1,12 +1,93 @@
-use crate::editor::DocumentStatus;
-use crate::terminal::Terminal;
+use crate::editor::{DocumentStatus, Mode};
+use crate::screen::Screen;
+use crate::theme::{Face, Theme};
 use crate::terminal::{Size, Position};
 use crate::uicomponent::UIComponent;
-use std::io::Error;
+use unicode_width::UnicodeWidthStr;
+
+const DEFAULT_FORMAT: &str = "%f %m%r - %L lines%=%s  %M  %t  %e  %n  Ln %l, Col %c";
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Field {
+    FileName,
+    Modified,
+    ReadOnly,
+    Line,
+    TotalLines,
+    Column,
+    FileType,
+    Encoding,
+    LineEnding,
+    Selection,
+    Mode,
+}
+
+#[derive(Clone, Eq, PartialEq)]
+enum Segment {
+    Text(String),
+    Field(Field),
+    Split,
+}
+
+// `%=` splits the left section from the right-aligned one
+#[derive(Clone, Eq, PartialEq)]
+pub struct StatusFormat {
+    left: Vec<Segment>,
+    right: Vec<Segment>,
+}
+
+impl Default for StatusFormat {
+    fn default() -> Self {
+        DEFAULT_FORMAT.parse().unwrap_or(Self{left: Vec::new(), right: Vec::new()})
+    }
+}
+
+impl std::str::FromStr for StatusFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut characters = format.chars();
+        while let Some(character) = characters.next() {
+            let segment = match character {
+                '%' => match characters.next() {
+                    Some('f') => Segment::Field(Field::FileName),
+                    Some('m') => Segment::Field(Field::Modified),
+                    Some('r') => Segment::Field(Field::ReadOnly),
+                    Some('l') => Segment::Field(Field::Line),
+                    Some('L') => Segment::Field(Field::TotalLines),
+                    Some('c') => Segment::Field(Field::Column),
+                    Some('t') => Segment::Field(Field::FileType),
+                    Some('e') => Segment::Field(Field::Encoding),
+                    Some('n') => Segment::Field(Field::LineEnding),
+                    Some('s') => Segment::Field(Field::Selection),
+                    Some('M') => Segment::Field(Field::Mode),
+                    Some('=') => Segment::Split,
+                    Some('%') => Segment::Text("%".to_string()),
+                    Some(other) => return Err(format!("unknown status field `%{other}`")),
+                    None => return Err("status format ends with `%`".to_string()),
+                },
+                _ => Segment::Text(character.to_string()),
+            };
+            match (segments.last_mut(), segment) {
+                (Some(Segment::Text(text)), Segment::Text(more)) => text.push_str(&more),
+                (_, segment) => segments.push(segment),
+            }
+        }
+        let mut sections = segments.split(|segment| *segment == Segment::Split);
+        let left = sections.next().unwrap_or_default().to_vec();
+        let right = sections.next().unwrap_or_default().to_vec();
+        if sections.next().is_some() {
+            return Err("status format has more than one `%=`".to_string());
+        }
+        Ok(Self{left, right})
+    }
+}
 
 pub struct StatusBar {
     needs_redraw: bool,
     document_status: DocumentStatus,
+    format: StatusFormat,
     size: Size,
 }
 
@@ -19,6 +100,105 @@ impl StatusBar {
         }
     }
 
+    pub fn set_format(&mut self, format: StatusFormat) {
+        self.format = format;
+        self.set_redraw(true);
+    }
+
+    fn field(&self, field: Field, file_name: &str) -> String {
+        let status = &self.document_status;
+        match field {
+            Field::FileName => file_name.to_string(),
+            Field::Modified => if status.is_modified { "[+]".to_string() } else { String::new() },
+            Field::ReadOnly => if status.readonly { "[RO]".to_string() } else { String::new() },
+            Field::Line => status.current_line.to_string(),
+            Field::TotalLines => status.total_lines.to_string(),
+            Field::Column => status.column.to_string(),
+            Field::FileType => status.file_type.name().to_string(),
+            Field::Encoding => status.encoding.name().to_string(),
+            Field::LineEnding => status.line_ending.name().to_string(),
+            Field::Selection => status.selection.map(|count| format!("{count} selected")).unwrap_or_default(),
+            Field::Mode => match status.mode {
+                Mode::Insert => "INSERT",
+                Mode::Select => "SELECT",
+                Mode::Prompt => "PROMPT",
+            }.to_string(),
+        }
+    }
+
+    // An empty field also drops the space in front of it, so missing flags leave no gaps
+    fn expand(&self, segments: &[Segment], file_name: &str) -> String {
+        let mut text = String::new();
+        for segment in segments {
+            match segment {
+                Segment::Text(more) => text.push_str(more),
+                Segment::Field(field) => {
+                    let value = self.field(*field, file_name);
+                    if value.is_empty() && text.ends_with(' ') {
+                        text.pop();
+                    }
+                    text.push_str(&value);
+                },
+                Segment::Split => (),
+            }
+        }
+        text.trim().to_string()
+    }
+
+    // Fits both sections into the bar, shortening the file name before anything else
+    fn layout(&self) -> String {
+        let width = self.size.num_columns;
+        let path = self.document_status.file_path.as_deref().unwrap_or("[No Name]");
+        let right = self.expand(&self.format.right, path);
+        let right = clip_start(&right, width.saturating_sub(2));
+        let mut left = self.expand(&self.format.left, path);
+        let available = width.saturating_sub(right.width() + 3);
+        if left.width() > available {
+            let others = self.expand(&self.format.left, "x").width().saturating_sub(1);
+            let shortened = shorten_path(path, available.saturating_sub(others));
+            left = clip_end(&self.expand(&self.format.left, &shortened), available);
+        }
+        let padding = width.saturating_sub(left.width() + right.width() + 2);
+        format!(" {left}{}{right} ", " ".repeat(padding))
+    }
+
+}
+
+// Drops leading directories first, then cuts the end of the file name itself
+fn shorten_path(path: &str, max_width: usize) -> String {
+    if path.width() <= max_width {
+        return path.to_string();
+    }
+    let components: Vec<&str> = path.split('/').collect();
+    for start in 1..components.len() {
+        let candidate = format!("…/{}", components[start..].join("/"));
+        if candidate.width() <= max_width {
+            return candidate;
+        }
+    }
+    clip_end(components.last().unwrap_or(&path), max_width)
+}
+
+fn clip_end(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    let mut clipped = String::new();
+    for character in text.chars() {
+        if clipped.width() + character.to_string().width() + 1 > max_width {
+            break;
+        }
+        clipped.push(character);
+    }
+    if max_width > 0 {
+        clipped.push('…');
+    }
+    clipped
+}
+
+fn clip_start(text: &str, max_width: usize) -> String {
+    let reversed: String = text.chars().rev().collect();
+    clip_end(&reversed, max_width).chars().rev().collect()
 }
 
 impl Default for StatusBar {
@@ -26,13 +206,14 @@ impl Default for StatusBar {
         Self{
             needs_redraw: true,
             document_status: DocumentStatus::default(),
+            format: StatusFormat::default(),
             size: Size::default(),
         }
     }
 }
 
 impl UIComponent for StatusBar {
-    
+
     fn set_redraw(&mut self, needs_redraw: bool) {
         self.needs_redraw = needs_redraw;
     }
@@ -45,24 +226,59 @@ impl UIComponent for StatusBar {
         self.size = size;
     }
 
-    fn draw(&mut self, start_row: usize) -> Result<(), Error> {
-        let DocumentStatus{file_path, current_line, total_lines, is_modified} = &self.document_status;
-        Terminal::move_cursor_to(Position{row: start_row, column: 0})?;
-        let string: String = if let Some(file_name) = file_path {
-            if *is_modified {
-                format!("{file_name} - {total_lines} lines (modified) {:^15}", format!("{current_line}/{total_lines}"))
-            }
-            else {
-                format!("{file_name} - {total_lines} lines {:^15}", format!("{current_line}/{total_lines}"))
-            }
-        }
-        else {
-            "".to_string()
-        };
-        Terminal::reverse_colour()?;
-        Terminal::print(&string)?;
-        Terminal::reset_colour()?;
-        Ok(())
+    fn draw(&mut self, screen: &mut Screen, theme: &Theme, start_row: usize) {
+        screen.move_cursor_to(Position{row: start_row, column: 0});
+        screen.set_style(theme.style(Face::StatusBar));
+        screen.clear_line();
+        screen.print(&self.layout());
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{StatusBar, StatusFormat};
+    use crate::editor::{DocumentStatus, Mode};
+    use crate::terminal::Size;
+    use crate::uicomponent::UIComponent;
+
+    fn status_bar(format: &str, num_columns: usize) -> StatusBar {
+        let mut status_bar = StatusBar::default();
+        status_bar.set_format(format.parse().unwrap());
+        status_bar.set_size(Size{num_rows: 1, num_columns});
+        status_bar.set_status(DocumentStatus{
+            file_path: Some("/home/user/projects/editor/src/statusbar.rs".to_string()),
+            current_line: 12,
+            total_lines: 340,
+            column: 7,
+            is_modified: true,
+            selection: Some(5),
+            mode: Mode::Select,
+            ..DocumentStatus::default()
+        });
+        status_bar
+    }
+
+    #[test]
+    fn right_section_is_right_aligned() {
+        let line = status_bar("%f%m%=%l:%c %M", 80).layout();
+        assert_eq!(line.chars().count(), 80);
+        assert!(line.starts_with(" /home/user/projects/editor/src/statusbar.rs[+] "));
+        assert!(line.ends_with(" 12:7 SELECT "));
+    }
+
+    #[test]
+    fn narrow_bars_shorten_the_file_name() {
+        assert_eq!(status_bar("%f %m%=%l/%L", 31).layout(), " …/src/statusbar.rs [+] 12/340 ");
+        assert_eq!(status_bar("%f%=%e %n %s", 24).layout(), " s… UTF-8 LF 5 selected ");
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!("%f %q".parse::<StatusFormat>().is_err());
+        assert!("100%".parse::<StatusFormat>().is_err());
+        assert!("100%%".parse::<StatusFormat>().is_ok());
     }
 
 }
\ No newline at end of file
@@ -1,8 +1,11 @@
 use crossterm::terminal::{enable_raw_mode, disable_raw_mode, Clear, ClearType, size, EnterAlternateScreen, LeaveAlternateScreen, EnableLineWrap, DisableLineWrap, SetTitle};
 use crossterm::{queue, Command};
 use crossterm::cursor::{MoveTo, Show, Hide};
-use crossterm::style::{Attribute, Print};
-use std::io::{stdout, Write, Error};
+use crossterm::event::{EnableMouseCapture, DisableMouseCapture};
+use crossterm::style::{Attribute, Print, SetBackgroundColor, SetForegroundColor};
+use crate::screen::{Screen, Style};
+use std::fs::OpenOptions;
+use std::io::{stdin, stdout, Error, IsTerminal, Write};
 
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub struct Size {
@@ -10,7 +13,7 @@ pub struct Size {
     pub num_columns: usize, 
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
 pub struct Position {
     pub row: usize,
     pub column: usize,
@@ -32,9 +35,11 @@ pub struct Terminal {
 impl Terminal {
     
     pub fn initialize() -> Result<(), Error> {
+        Self::check_keyboard_input()?;
         enable_raw_mode()?;
         Self::enter_alternate_screen()?;
         Self::disable_line_wrap()?;
+        Self::queue_command(EnableMouseCapture)?;
         Self::clear_screen()?;
         Self::move_cursor_to(Position{row: 0, column: 0})?;
         Self::execute()?;
@@ -43,12 +48,22 @@ impl Terminal {
 
     pub fn terminate() -> Result<(), Error> {
         Self::execute()?;
-        Self::disable_line_wrap()?;
+        Self::queue_command(DisableMouseCapture)?;
+        Self::enable_line_wrap()?;
         Self::leave_alternate_screen()?;
         disable_raw_mode()?;
         Ok(())
     }
 
+    // When stdin is a pipe, crossterm switches to reading keys from /dev/tty instead
+    fn check_keyboard_input() -> Result<(), Error> {
+        if !stdin().is_terminal() {
+            OpenOptions::new().read(true).write(true).open("/dev/tty")
+                .map_err(|err| Error::new(err.kind(), format!("no terminal available for keyboard input: {err}")))?;
+        }
+        Ok(())
+    }
+
     pub fn execute() -> Result<(), Error> {
         stdout().flush()?;
         Ok(())
@@ -79,8 +94,43 @@ impl Terminal {
         Ok(())
     }
 
-    pub fn reverse_colour() -> Result<(), Error> {
-        Self::queue_command(Print(Attribute::Reverse))?;
+    // Only emits the cells that changed since `previous` was drawn
+    pub fn render(previous: &Screen, current: &Screen) -> Result<(), Error> {
+        let mut style = Style::default();
+        let mut cursor = None;
+        Self::reset_colour()?;
+        for (position, cell) in current.changes(previous) {
+            if cursor != Some(position) {
+                Self::move_cursor_to(position)?;
+            }
+            if cell.style != style {
+                Self::set_style(cell.style)?;
+                style = cell.style;
+            }
+            Self::print(&cell.symbol)?;
+            cursor = Some(Position{row: position.row, column: position.column + cell.width});
+        }
+        Self::reset_colour()?;
+        Ok(())
+    }
+
+    fn set_style(style: Style) -> Result<(), Error> {
+        Self::reset_colour()?;
+        if style.bold {
+            Self::queue_command(Print(Attribute::Bold))?;
+        }
+        if style.dim {
+            Self::queue_command(Print(Attribute::Dim))?;
+        }
+        if style.reverse {
+            Self::queue_command(Print(Attribute::Reverse))?;
+        }
+        if let Some(colour) = style.foreground {
+            Self::queue_command(SetForegroundColor(colour))?;
+        }
+        if let Some(colour) = style.background {
+            Self::queue_command(SetBackgroundColor(colour))?;
+        }
         Ok(())
     }
 
@@ -94,11 +144,6 @@ impl Terminal {
         Ok(())
     }
 
-    pub fn clear_line() -> Result<(), Error> {
-        Self::queue_command(Clear(ClearType::CurrentLine))?;
-        Ok(())
-    }
-
     pub fn clear_screen() -> Result<(), Error> {
         Self::queue_command(Clear(ClearType::All))?;
         Ok(())
@@ -115,7 +160,9 @@ impl Terminal {
     }
 
     pub fn move_cursor_to(position: Position) -> Result<(), Error> {
-        Self::queue_command(MoveTo(position.column as u16, position.row as u16))?;
+        let column = u16::try_from(position.column).unwrap_or(u16::MAX);
+        let row = u16::try_from(position.row).unwrap_or(u16::MAX);
+        Self::queue_command(MoveTo(column, row))?;
         Ok(())
     }
 
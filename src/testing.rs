@@ -0,0 +1,188 @@
+use crate::backend::{Backend, EventSource};
+use crate::cli::{Arguments, FileArgument, FileSource};
+use crate::config::Config;
+use crate::editor::Editor;
+use crate::screen::{Screen, Style};
+use crate::terminal::{Size, Position};
+use crate::theme::ColourDepth;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use std::{cell::RefCell, collections::VecDeque, env, fs, io::Error, path::PathBuf, process, rc::Rc, time::Duration};
+
+#[derive(Default)]
+struct Recording {
+    rows: Vec<Vec<String>>,
+    cursor: Position,
+    cursor_visible: bool,
+    title: String,
+    output: String,
+    screen: Option<Screen>,
+}
+
+// Records what a real terminal would show; clones share the same recording
+#[derive(Clone)]
+pub struct MemoryBackend {
+    size: Size,
+    recording: Rc<RefCell<Recording>>,
+}
+
+impl MemoryBackend {
+
+    pub fn new(size: Size) -> Self {
+        Self{size, recording: Rc::default()}
+    }
+
+    pub fn row(&self, row: usize) -> String {
+        self.recording.borrow().rows.get(row).map(|cells| cells.concat()).unwrap_or_default()
+    }
+
+    pub fn cursor(&self) -> Option<Position> {
+        let recording = self.recording.borrow();
+        recording.cursor_visible.then_some(recording.cursor)
+    }
+
+    pub fn style(&self, position: Position) -> Style {
+        self.recording.borrow().screen.as_ref().and_then(|screen| screen.cell(position)).map(|cell| cell.style).unwrap_or_default()
+    }
+
+    pub fn title(&self) -> String {
+        self.recording.borrow().title.clone()
+    }
+
+    pub fn output(&self) -> String {
+        self.recording.borrow().output.clone()
+    }
+
+}
+
+impl Backend for MemoryBackend {
+
+    fn initialize(&mut self) -> Result<(), Error> {
+        self.clear_screen()
+    }
+
+    fn terminate(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn size(&self) -> Result<Size, Error> {
+        Ok(self.size)
+    }
+
+    fn colour_depth(&self) -> ColourDepth {
+        ColourDepth::TrueColour
+    }
+
+    fn set_title(&mut self, title: &str) -> Result<(), Error> {
+        self.recording.borrow_mut().title = title.to_string();
+        Ok(())
+    }
+
+    fn clear_screen(&mut self) -> Result<(), Error> {
+        self.recording.borrow_mut().rows = vec![vec![" ".to_string(); self.size.num_columns]; self.size.num_rows];
+        Ok(())
+    }
+
+    fn render(&mut self, previous: &Screen, current: &Screen) -> Result<(), Error> {
+        let mut recording = self.recording.borrow_mut();
+        for (Position{row, column}, cell) in current.changes(previous) {
+            let cells = &mut recording.rows[row];
+            cells[column].clone_from(&cell.symbol);
+            for offset in 1..cell.width {
+                cells[column + offset].clear();
+            }
+        }
+        recording.screen = Some(current.clone());
+        Ok(())
+    }
+
+    fn move_cursor_to(&mut self, position: Position) -> Result<(), Error> {
+        self.recording.borrow_mut().cursor = position;
+        Ok(())
+    }
+
+    fn show_cursor(&mut self) -> Result<(), Error> {
+        self.recording.borrow_mut().cursor_visible = true;
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> Result<(), Error> {
+        self.recording.borrow_mut().cursor_visible = false;
+        Ok(())
+    }
+
+    fn print(&mut self, string: &str) -> Result<(), Error> {
+        self.recording.borrow_mut().output.push_str(string);
+        Ok(())
+    }
+
+    fn execute(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+}
+
+// Replays a fixed list of events, then stops the editor
+pub struct ScriptedEvents(VecDeque<Event>);
+
+impl EventSource for ScriptedEvents {
+
+    fn poll(&mut self, _timeout: Duration) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, Error> {
+        Ok(self.0.pop_front())
+    }
+
+}
+
+pub fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+    Event::Key(KeyEvent::new(code, modifiers))
+}
+
+pub fn mouse(kind: MouseEventKind, row: u16, column: u16) -> Event {
+    Event::Mouse(MouseEvent{kind, column, row, modifiers: KeyModifiers::NONE})
+}
+
+pub fn ctrl(character: char) -> Event {
+    key(KeyCode::Char(character), KeyModifiers::CONTROL)
+}
+
+pub fn text(string: &str) -> Vec<Event> {
+    string
+        .chars()
+        .map(|character| match character {
+            '\n' => key(KeyCode::Enter, KeyModifiers::NONE),
+            '\t' => key(KeyCode::Tab, KeyModifiers::NONE),
+            _ => key(KeyCode::Char(character), KeyModifiers::NONE),
+        })
+        .collect()
+}
+
+// Runs an editor on `files` until the events run out, returning what ended up on screen
+pub fn run(files: &[&str], config: &Config, events: Vec<Event>) -> MemoryBackend {
+    let backend = MemoryBackend::new(Size{num_rows: 10, num_columns: 80});
+    let arguments = Arguments{
+        files: files.iter().map(|file| FileArgument{source: FileSource::Path((*file).to_string()), target: None}).collect(),
+        ..Arguments::default()
+    };
+    let mut editor = Editor::with_backend(arguments, config, Box::new(backend.clone()), Box::new(ScriptedEvents(events.into()))).unwrap();
+    editor.run();
+    backend
+}
+
+// Writes `contents` to the first of `files`, runs the editor on them, saves, and returns what ended up in that file
+pub fn edit(files: &[&str], contents: &str, mut events: Vec<Event>, config: &Config) -> String {
+    fs::write(files[0], contents).unwrap();
+    events.push(ctrl('s'));
+    run(files, config, events);
+    fs::read_to_string(files[0]).unwrap()
+}
+
+// A scratch directory unique to the calling test
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("editor-test-{}-{name}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
\ No newline at end of file
@@ -0,0 +1,258 @@
+use crate::config::parse_settings;
+use crate::screen::Style;
+use crossterm::style::Color;
+use std::{collections::HashMap, env, fs, path::Path};
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Face {
+    Text,
+    Gutter,
+    GutterCurrent,
+    StatusBar,
+    MessageBar,
+    MessageWarning,
+    MessageError,
+    Selection,
+    SearchMatch,
+    MatchingBracket,
+    SecondaryCursor,
+    Completion,
+    CompletionSelected,
+    Whitespace,
+    Keyword,
+    String,
+    Number,
+    Comment,
+    Type,
+}
+
+impl Face {
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Self::Text),
+            "gutter" => Some(Self::Gutter),
+            "gutter_current" => Some(Self::GutterCurrent),
+            "status_bar" => Some(Self::StatusBar),
+            "message_bar" => Some(Self::MessageBar),
+            "message_warning" => Some(Self::MessageWarning),
+            "message_error" => Some(Self::MessageError),
+            "selection" => Some(Self::Selection),
+            "search_match" => Some(Self::SearchMatch),
+            "matching_bracket" => Some(Self::MatchingBracket),
+            "secondary_cursor" => Some(Self::SecondaryCursor),
+            "completion" => Some(Self::Completion),
+            "completion_selected" => Some(Self::CompletionSelected),
+            "whitespace" => Some(Self::Whitespace),
+            "keyword" => Some(Self::Keyword),
+            "string" => Some(Self::String),
+            "number" => Some(Self::Number),
+            "comment" => Some(Self::Comment),
+            "type" => Some(Self::Type),
+            _ => None,
+        }
+    }
+
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum ColourDepth {
+    TrueColour,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColourDepth {
+
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            Self::TrueColour
+        }
+        else if term.contains("256") {
+            Self::Ansi256
+        }
+        else {
+            Self::Ansi16
+        }
+    }
+
+    // Picks the closest colour the terminal can show
+    pub fn convert(self, colour: Color) -> Color {
+        let Color::Rgb{r, g, b} = colour else {
+            return colour;
+        };
+        match self {
+            Self::TrueColour => colour,
+            Self::Ansi256 => Color::AnsiValue(nearest_ansi256([r, g, b])),
+            Self::Ansi16 => nearest_ansi16([r, g, b]),
+        }
+    }
+
+}
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+const ANSI16: [(Color, [u8; 3]); 16] = [
+    (Color::Black, [0, 0, 0]),
+    (Color::DarkRed, [205, 0, 0]),
+    (Color::DarkGreen, [0, 205, 0]),
+    (Color::DarkYellow, [205, 205, 0]),
+    (Color::DarkBlue, [0, 0, 238]),
+    (Color::DarkMagenta, [205, 0, 205]),
+    (Color::DarkCyan, [0, 205, 205]),
+    (Color::Grey, [229, 229, 229]),
+    (Color::DarkGrey, [127, 127, 127]),
+    (Color::Red, [255, 0, 0]),
+    (Color::Green, [0, 255, 0]),
+    (Color::Yellow, [255, 255, 0]),
+    (Color::Blue, [92, 92, 255]),
+    (Color::Magenta, [255, 0, 255]),
+    (Color::Cyan, [0, 255, 255]),
+    (Color::White, [255, 255, 255]),
+];
+
+fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter().zip(b).map(|(x, y)| u32::from(x.abs_diff(y)).pow(2)).sum()
+}
+
+// Whichever is closer out of the 6x6x6 colour cube and the 24 step grey ramp
+fn nearest_ansi256(rgb: [u8; 3]) -> u8 {
+    let cube_index = |value: u8| CUBE_LEVELS.iter().enumerate().min_by_key(|(_, level)| level.abs_diff(value)).map_or(0, |(index, _)| index);
+    let [r, g, b] = rgb.map(cube_index);
+    let cube = [CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]];
+    let average = (rgb.iter().map(|value| u32::from(*value)).sum::<u32>() / 3).min(238);
+    let grey_index = u8::try_from(average.saturating_sub(3) / 10).unwrap_or(23).min(23);
+    let grey_level = 8 + grey_index * 10;
+    if distance(rgb, [grey_level; 3]) < distance(rgb, cube) {
+        232 + grey_index
+    }
+    else {
+        u8::try_from(16 + 36 * r + 6 * g + b).unwrap_or(0)
+    }
+}
+
+fn nearest_ansi16(rgb: [u8; 3]) -> Color {
+    ANSI16.iter().min_by_key(|(_, value)| distance(rgb, *value)).map_or(Color::Reset, |(colour, _)| *colour)
+}
+
+#[derive(Clone)]
+pub struct Theme {
+    styles: HashMap<Face, Style>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let bold = Style{bold: true, ..Style::default()};
+        let dim = Style{dim: true, ..Style::default()};
+        let reverse = Style{reverse: true, ..Style::default()};
+        let bold_reverse = Style{bold: true, reverse: true, ..Style::default()};
+        let styles = HashMap::from([
+            (Face::GutterCurrent, bold),
+            (Face::StatusBar, reverse),
+            (Face::Selection, reverse),
+            (Face::SearchMatch, reverse),
+            (Face::SecondaryCursor, bold_reverse),
+            (Face::Completion, reverse),
+            (Face::CompletionSelected, bold),
+            (Face::MatchingBracket, bold_reverse),
+            (Face::Whitespace, dim),
+            (Face::MessageWarning, Style{foreground: Some(Color::DarkYellow), ..Style::default()}),
+            (Face::MessageError, Style{foreground: Some(Color::Red), bold: true, ..Style::default()}),
+        ]);
+        Self{styles}
+    }
+}
+
+impl Theme {
+
+    // Faces left out of the file keep their default style
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+        Self::parse(&contents).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    fn parse(contents: &str) -> Result<Self, String> {
+        let mut theme = Self::default();
+        parse_settings(contents, |key, value| {
+            let face = Face::from_name(key).ok_or_else(|| format!("unknown face `{key}`"))?;
+            theme.styles.insert(face, parse_style(value)?);
+            Ok(())
+        })?;
+        Ok(theme)
+    }
+
+    pub fn style(&self, face: Face) -> Style {
+        self.styles.get(&face).copied().unwrap_or_default()
+    }
+
+    pub fn with_depth(mut self, depth: ColourDepth) -> Self {
+        for style in self.styles.values_mut() {
+            style.foreground = style.foreground.map(|colour| depth.convert(colour));
+            style.background = style.background.map(|colour| depth.convert(colour));
+        }
+        self
+    }
+
+}
+
+// A space separated list such as `fg=#e0e0e0 bg=#202020 bold`
+fn parse_style(value: &str) -> Result<Style, String> {
+    let mut style = Style::default();
+    for word in value.split_whitespace() {
+        match word.split_once('=') {
+            Some(("fg", colour)) => style.foreground = Some(parse_colour(colour)?),
+            Some(("bg", colour)) => style.background = Some(parse_colour(colour)?),
+            None if word == "bold" => style.bold = true,
+            None if word == "dim" => style.dim = true,
+            None if word == "reverse" => style.reverse = true,
+            None if word == "plain" => (),
+            _ => return Err(format!("unknown style `{word}`")),
+        }
+    }
+    Ok(style)
+}
+
+fn parse_colour(value: &str) -> Result<Color, String> {
+    let invalid = || format!("invalid colour `{value}`, expected #rrggbb");
+    let hex = value.strip_prefix('#').filter(|hex| hex.len() == 6 && hex.is_ascii()).ok_or_else(invalid)?;
+    let channel = |range| u8::from_str_radix(&hex[range], 16).map_err(|_| invalid());
+    Ok(Color::Rgb{r: channel(0..2)?, g: channel(2..4)?, b: channel(4..6)?})
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{ColourDepth, Face, Theme};
+    use crossterm::style::Color;
+
+    #[test]
+    fn parses_faces_over_the_defaults() {
+        let theme = Theme::parse("status_bar = fg=#ffffff bg=#005f87 bold\n# comment\ncomment = dim").unwrap();
+        let status_bar = theme.style(Face::StatusBar);
+        assert_eq!(status_bar.foreground, Some(Color::Rgb{r: 255, g: 255, b: 255}));
+        assert_eq!(status_bar.background, Some(Color::Rgb{r: 0, g: 95, b: 135}));
+        assert!(status_bar.bold && !status_bar.reverse);
+        assert!(theme.style(Face::Comment).dim);
+        assert!(theme.style(Face::Selection).reverse);
+    }
+
+    #[test]
+    fn rejects_unknown_faces_and_colours() {
+        assert_eq!(Theme::parse("cursor = bold").err().unwrap(), "line 1: unknown face `cursor`");
+        assert!(Theme::parse("text = fg=red").is_err());
+        assert!(Theme::parse("text = fg=#12345").is_err());
+    }
+
+    #[test]
+    fn degrades_to_fewer_colours() {
+        let orange = Color::Rgb{r: 255, g: 135, b: 0};
+        assert_eq!(ColourDepth::TrueColour.convert(orange), orange);
+        assert_eq!(ColourDepth::Ansi256.convert(orange), Color::AnsiValue(208));
+        assert_eq!(ColourDepth::Ansi256.convert(Color::Rgb{r: 128, g: 128, b: 128}), Color::AnsiValue(244));
+        assert_eq!(ColourDepth::Ansi16.convert(orange), Color::DarkYellow);
+        assert_eq!(ColourDepth::Ansi16.convert(Color::Rgb{r: 10, g: 10, b: 200}), Color::DarkBlue);
+    }
+
+}
\ No newline at end of file
@@ -1,4 +1,5 @@
-use std::io::Error;
+use crate::screen::Screen;
+use crate::theme::Theme;
 use crate::terminal::Size;
 
 pub trait UIComponent {
@@ -14,21 +15,13 @@ pub trait UIComponent {
 
     fn set_size(&mut self, size: Size);
 
-    fn render(&mut self, start_row: usize) {
+    fn render(&mut self, screen: &mut Screen, theme: &Theme, start_row: usize) {
         if self.needs_redraw() {
-            match self.draw(start_row) {
-                Ok(()) => self.set_redraw(false),
-                Err(err) => {
-                    #[cfg(debug_assertions)]
-                    {
-                        panic!("Could not render component: {err:?}");
-                    }
-                }
-            }
+            self.draw(screen, theme, start_row);
         }
         self.set_redraw(false);
     }
 
-    fn draw(&mut self, start_row: usize) -> Result<(), Error>;
+    fn draw(&mut self, screen: &mut Screen, theme: &Theme, start_row: usize);
 
 }
\ No newline at end of file
@@ -1,91 +1,762 @@
-use crate::editor::{Location, DocumentStatus};
-use crate::editorcommand::{EditorCommand, Direction, EditorCommand::{Move, Insert, Backspace, Delete, Enter, Save}};
-use crate::terminal::{Terminal, Position, Size};
+use crate::editor::{Location, DocumentStatus, Mode};
+use crate::filetype::FileType;
+use crate::editorcommand::{EditorCommand, Direction, MouseAction, EditorCommand::{Move, Insert, Backspace, Delete, DeleteWordBackward, DeleteWordForward, Dedent, ToggleComment, DuplicateLines, MoveLinesUp, MoveLinesDown, DeleteLines, JoinLines, SortLines, JumpToBracket, AddCursorAbove, AddCursorBelow, AddNextOccurrence, Enter, KillLine, KillRegion, CopyRegion, Yank, YankPop, Undo, Redo, SetMark, ToggleBlockSelection, ClearMark, ToggleLineNumbers, ToggleWrap, ToggleWhitespace, Mouse, Scroll, ScrollLines, ScrollHalfPage, Recenter}};
+use crate::terminal::{Position, Size};
+use crate::screen::{Screen, Style};
+use crate::theme::{Face, Theme};
 use crate::buffer::Buffer;
+use crate::history::Edit;
+use crate::completion::Candidates;
+use crate::encoding::Encoding;
+use crate::killring::KillRing;
+use crate::goto::GotoTarget;
+use crate::config::{LineNumbers, ViewSettings, WrapMode};
 use crate::uicomponent::UIComponent;
-use std::{cmp, io::Error};
+use crate::line::{GlyphKind, Line};
+use std::{cmp, io::{Error, ErrorKind, Read}, ops::Range};
 
 const NAME: &str = env!("CARGO_PKG_NAME");
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+const PAIRS: [(char, char); 5] = [('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')];
+
+// The editing state each cursor keeps for itself
+#[derive(Copy, Clone, Default)]
+struct Cursor {
+    location: Location,
+    mark: Option<Location>,
+    desired_column: Option<usize>,
+}
+
+impl Cursor {
+
+    fn range(&self) -> (Location, Location) {
+        let mark = self.mark.unwrap_or(self.location);
+        (self.location.min(mark), self.location.max(mark))
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        let (start, end) = self.range();
+        let (other_start, other_end) = other.range();
+        (start, end) == (other_start, other_end) || start < other_end && other_start < end
+    }
+
+    fn shift(&mut self, edit: &Edit) {
+        self.location = edit.shift(self.location);
+        self.mark = self.mark.map(|mark| edit.shift(mark));
+    }
+
+}
+
+#[derive(Copy, Clone)]
+enum RecenterPosition {
+    Center,
+    Top,
+    Bottom,
+}
+
 #[derive(Default)]
 pub struct View {
     buffer: Buffer,
     needs_redraw: bool,
     size: Size,
     top_left: Location,
+    top_row: usize,
     location: Location,
+    desired_column: Option<usize>,
+    mark: Option<Location>,
+    // The display column of the mark while selecting a block
+    block_column: Option<usize>,
+    cursors: Vec<Cursor>,
+    kill_in_progress: bool,
+    last_yank: Option<(Location, Location)>,
+    last_recenter: Option<RecenterPosition>,
+    // Found once per command rather than on every redraw, since matching can scan many lines
+    bracket_pair: Option<(Location, Location)>,
+    // Closers typed by auto-pairing, with the edit count they were placed at; only these are stepped over or deleted with their opener
+    auto_closers: Vec<(Location, usize)>,
+    readonly: bool,
+    settings: ViewSettings,
+    scratch_name: Option<String>,
 }
 
 impl View {
 
-    pub fn new(size: Size) -> Self {
-        Self {
-            buffer: Buffer::default(),
-            needs_redraw: true,
-            size,
-            top_left: Location::default(),
-            location: Location::default(),
+    pub fn set_settings(&mut self, settings: ViewSettings) {
+        self.settings = settings;
+        self.set_redraw(true);
+    }
+
+    pub fn load(&mut self, file_path: &str, encoding: Encoding) -> Result<(), Error> {
+        match Buffer::load(file_path, encoding) {
+            Ok(buffer) => self.buffer = buffer,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                self.buffer = Buffer::from_text("", encoding);
+                self.buffer.save_file_path = Some(file_path.to_string());
+            },
+            Err(err) => return Err(err),
         }
+        self.update_bracket_pair();
+        Ok(())
+    }
+
+    pub fn load_stdin(&mut self, encoding: Encoding) -> Result<(), Error> {
+        let mut contents = Vec::new();
+        std::io::stdin().read_to_end(&mut contents)?;
+        self.buffer = Buffer::from_text(&encoding.decode(contents)?, encoding);
+        self.update_bracket_pair();
+        Ok(())
+    }
+
+    pub fn set_readonly(&mut self, readonly: bool) {
+        self.readonly = readonly;
     }
 
-    pub fn load(&mut self, file_path: &str) {
-        if let Ok(buffer) = Buffer::load(file_path) {
-            self.buffer = buffer;
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    // A read-only buffer that is not backed by a file, such as the message history
+    pub fn scratch(name: &str, text: &str, settings: ViewSettings) -> Self {
+        Self{
+            buffer: Buffer::from_text(text, Encoding::default()),
+            readonly: true,
+            settings,
+            scratch_name: Some(name.to_string()),
+            ..Self::default()
         }
     }
 
+    pub fn scratch_name(&self) -> Option<&str> {
+        self.scratch_name.as_deref()
+    }
+
+    pub fn settings(&self) -> ViewSettings {
+        self.settings
+    }
+
+    pub fn get_file_name(&self) -> String {
+        self.buffer.save_file_path.clone().or_else(|| self.scratch_name.clone()).unwrap_or_else(|| "[No Name]".to_string())
+    }
+
     pub fn get_status(&self) -> DocumentStatus {
         let total_lines = self.buffer.get_num_rows();
+        let region = self.region();
+        let selection = match self.block() {
+            Some((lines, columns)) => Some(self.block_text(lines, &columns).chars().count()),
+            None => region.map(|(start, end)| self.buffer.get_text(start, end).chars().count()),
+        };
         DocumentStatus{
-            file_path: self.buffer.save_file_path.clone(),
+            file_path: self.buffer.save_file_path.clone().or_else(|| self.scratch_name.clone()),
             current_line: cmp::min(self.location.y + 1, total_lines),
             total_lines,
-            is_modified: self.buffer.is_modified}
+            column: self.cursor_column() + 1,
+            is_modified: self.buffer.is_modified,
+            readonly: self.readonly,
+            file_type: self.file_type(),
+            encoding: self.buffer.encoding,
+            line_ending: self.buffer.line_ending,
+            selection,
+            mode: if region.is_some() { Mode::Select } else { Mode::Insert },
+        }
+    }
+
+    pub fn file_type(&self) -> FileType {
+        self.buffer.save_file_path.as_deref().map(FileType::from_path).unwrap_or_default()
+    }
+
+    // Every command is one undo step, except that a run of typed characters is undone together.
+    // The kill ring belongs to the editor, so text killed in one buffer can be yanked into another
+    pub fn handle_command(&mut self, command: EditorCommand, kill_ring: &mut KillRing) {
+        let merge = matches!(command, Insert(character) if !character.is_whitespace());
+        self.buffer.begin_change(self.location, merge);
+        self.shift_auto_closers();
+        let is_shared = matches!(command, Move(_) | Insert(_) | Backspace | Delete | DeleteWordBackward | DeleteWordForward | Enter);
+        if self.block().is_some() && matches!(command, Insert(_) | Backspace | Delete | KillRegion | CopyRegion) {
+            self.edit_block(command, kill_ring);
+        }
+        else if is_shared && !self.cursors.is_empty() {
+            self.execute_at_every_cursor(command, kill_ring);
+        }
+        else {
+            let keeps_cursors = matches!(command, Scroll(_) | ScrollLines(_) | ScrollHalfPage(_) | Recenter | ToggleLineNumbers |
+                ToggleWrap | ToggleWhitespace | AddCursorAbove | AddCursorBelow | AddNextOccurrence | Mouse(MouseAction::AddCursor, _));
+            if !keeps_cursors && !self.cursors.is_empty() {
+                self.cursors.clear();
+                self.set_redraw(true);
+            }
+            self.execute(command, kill_ring);
+        }
+        if self.mark.is_none() {
+            self.block_column = None;
+        }
+        if matches!(command, Insert(_) | Backspace) {
+            self.shift_auto_closers();
+        }
+        else {
+            self.auto_closers.clear();
+        }
+        self.buffer.end_change(self.location);
+        self.update_bracket_pair();
+    }
+
+    // The word just before the cursor and where it starts on screen, if there is one to complete
+    pub fn completion_prefix(&self) -> Option<(String, Position)> {
+        if self.mark.is_some() || !self.cursors.is_empty() {
+            return None;
+        }
+        let Location{x, y} = self.location;
+        let line = self.buffer.get_line(y)?;
+        let word = line.word_ending_at(x)?;
+        let position = self.screen_position(Location{x: word.start, y})?;
+        Some((line.get(word), position))
+    }
+
+    // Distances only count in the buffer being edited, so its words rank above those from other buffers
+    pub fn collect_words(&self, prefix: &str, is_current: bool, candidates: &mut Candidates) {
+        for y in 0..self.buffer.get_num_rows() {
+            let Some(line) = self.buffer.get_line(y) else {
+                continue;
+            };
+            let distance = if is_current { y.abs_diff(self.location.y) } else { usize::MAX };
+            for word in line.words().filter(|word| word.len() > prefix.len() && word.starts_with(prefix)) {
+                candidates.add(word, distance);
+            }
+        }
+    }
+
+    pub fn complete(&mut self, word: &str) {
+        let Some(rest) = self.completion_prefix().and_then(|(prefix, _)| word.strip_prefix(prefix.as_str()).map(str::to_string)) else {
+            return;
+        };
+        self.buffer.begin_change(self.location, false);
+        self.shift_auto_closers();
+        self.location = self.buffer.insert(self.location, &rest);
+        self.shift_auto_closers();
+        self.buffer.end_change(self.location);
+        self.desired_column = None;
+        self.update_cursor_position();
+        self.update_bracket_pair();
+    }
+
+    fn cursor(&self) -> Cursor {
+        Cursor{location: self.location, mark: self.mark, desired_column: self.desired_column}
+    }
+
+    fn set_cursor(&mut self, cursor: Cursor) {
+        self.location = cursor.location;
+        self.mark = cursor.mark;
+        self.desired_column = cursor.desired_column;
+    }
+
+    // Runs the command at each cursor in turn, shifting the others through its edits so they stay on the same text
+    fn execute_at_every_cursor(&mut self, command: EditorCommand, kill_ring: &mut KillRing) {
+        let (top_left, top_row) = (self.top_left, self.top_row);
+        let mut cursors = std::mem::take(&mut self.cursors);
+        cursors.push(self.cursor());
+        for index in 0..cursors.len() {
+            // The primary cursor goes last and from the original scroll position, so the view follows only it
+            if index + 1 == cursors.len() {
+                (self.top_left, self.top_row) = (top_left, top_row);
+            }
+            self.set_cursor(cursors[index]);
+            let edit_count = self.buffer.edit_count();
+            self.execute(command, kill_ring);
+            cursors[index] = self.cursor();
+            for edit in self.buffer.edits_since(edit_count) {
+                for (other, cursor) in cursors.iter_mut().enumerate() {
+                    if other != index {
+                        cursor.shift(&edit);
+                    }
+                }
+            }
+        }
+        if let Some(primary) = cursors.pop() {
+            self.set_cursor(primary);
+        }
+        self.cursors = cursors;
+        self.merge_cursors();
+        self.set_redraw(true);
+    }
+
+    // Cursors that end up on the same spot or with overlapping selections become one
+    fn merge_cursors(&mut self) {
+        let primary = self.cursor();
+        self.cursors.retain(|cursor| !cursor.overlaps(&primary));
+        self.cursors.sort_by_key(|cursor| cursor.range().0);
+        self.cursors.dedup_by(|cursor, previous| cursor.overlaps(previous));
+    }
+
+    // The new cursor becomes the primary one, so the view follows it
+    fn add_cursor(&mut self, cursor: Cursor) {
+        self.cursors.push(self.cursor());
+        self.set_cursor(cursor);
+        self.merge_cursors();
+        self.update_cursor_position();
     }
 
-    pub fn handle_command(&mut self, command: EditorCommand) {
+    fn add_column_cursor(&mut self, down: bool) {
+        let cursors = self.cursors.iter().copied().chain(std::iter::once(self.cursor()));
+        let edge = if down { cursors.max_by_key(|cursor| cursor.location) } else { cursors.min_by_key(|cursor| cursor.location) };
+        let Some(edge) = edge else {
+            return;
+        };
+        let Some(y) = (if down { edge.location.y.checked_add(1) } else { edge.location.y.checked_sub(1) }) else {
+            return;
+        };
+        let Some(line) = self.buffer.get_line(y) else {
+            return;
+        };
+        let column = edge.desired_column.unwrap_or_else(|| self.column_of(edge.location));
+        let x = line.index_at_column(column, self.settings.tab_width);
+        self.add_cursor(Cursor{location: Location{x, y}, mark: None, desired_column: Some(column)});
+    }
+
+    // Selects the word under the cursor first, then adds a cursor at each following match of the selection
+    fn add_next_occurrence(&mut self) {
+        let Some((start, end)) = self.region() else {
+            let Location{x, y} = self.location;
+            let word = self.buffer.get_line(y).map_or(x..x, |line| line.word_at(x));
+            if !word.is_empty() {
+                self.mark = Some(Location{x: word.start, y});
+                self.location = Location{x: word.end, y};
+                self.update_cursor_position();
+            }
+            return;
+        };
+        let needle = self.buffer.get_text(start, end);
+        if start.y != end.y {
+            return;
+        }
+        let Some(found) = self.buffer.find(&needle, end).or_else(|| self.buffer.find(&needle, Location::default())) else {
+            return;
+        };
+        let found_end = Location{x: found.x + Line::from(&needle).len(), y: found.y};
+        self.add_cursor(Cursor{location: found_end, mark: Some(found), desired_column: None});
+    }
+
+    fn execute(&mut self, command: EditorCommand, kill_ring: &mut KillRing) {
+        let continues_kill = std::mem::take(&mut self.kill_in_progress);
+        let last_yank = self.last_yank.take();
+        let last_recenter = self.last_recenter.take();
+        let is_vertical = matches!(command, Move(Direction::Up | Direction::Down | Direction::PageUp | Direction::PageDown));
+        if command.is_edit() || matches!(command, Move(_) | Mouse(..)) && !is_vertical {
+            self.desired_column = None;
+        }
+        let is_block = self.region().is_some_and(|(start, end)| start.y != end.y);
+        let is_block_edit = is_block && matches!(command, Insert('\t') | Dedent);
+        if matches!(command, Insert(_) | Backspace | Delete | DeleteWordBackward | DeleteWordForward | Dedent | Enter | Undo | Redo) && !is_block_edit {
+            self.clear_mark();
+        }
         match command {
             Move(direction) => {
-                self.move_cursor(&direction);
+                self.move_cursor(direction);
                 self.set_redraw(true);
             },
+            Insert('\t') if is_block => self.indent_lines(self.selected_lines()),
+            Insert('\t') if self.settings.soft_tabs => {
+                let tab_width = self.settings.tab_width;
+                let spaces = tab_width - self.cursor_column() % tab_width;
+                self.location = self.buffer.insert(self.location, &" ".repeat(spaces));
+                self.update_cursor_position();
+            },
             Insert(character) => {
                 self.add_character(character);
                 self.set_redraw(true);
             },
-            Backspace if ((self.location.x > 0) | (self.location.y > 0)) => {
-                self.move_cursor(&Direction::Left);
-                self.handle_command(Delete);
+            Dedent => self.dedent_lines(self.selected_lines()),
+            ToggleComment => self.toggle_comment(),
+            JumpToBracket => self.jump_to_bracket(),
+            AddCursorAbove => self.add_column_cursor(false),
+            AddCursorBelow => self.add_column_cursor(true),
+            AddNextOccurrence => self.add_next_occurrence(),
+            DuplicateLines => self.duplicate_lines(),
+            MoveLinesUp => self.move_lines(false),
+            MoveLinesDown => self.move_lines(true),
+            DeleteLines => self.delete_lines(),
+            JoinLines => self.join_lines(),
+            SortLines => self.sort_lines(),
+            Undo => self.undo(false),
+            Redo => self.undo(true),
+            Backspace if self.settings.auto_pairs && self.is_inside_empty_pair() => self.delete_pair(),
+            Backspace if self.location.x > 0 || self.location.y > 0 => {
+                self.move_cursor(Direction::Left);
+                self.execute(Delete, kill_ring);
                 self.set_redraw(true);
             },
-            Delete if (self.location.x < self.buffer.get_num_columns(self.location.y)) |
-                        (self.location.y < self.buffer.get_num_rows() - 1) => {
+            Delete if self.location.x < self.buffer.get_num_columns(self.location.y) ||
+                        self.location.y + 1 < self.buffer.get_num_rows() => {
                 self.delete_character();
                 self.set_redraw(true);
             },
+            DeleteWordBackward => {
+                let start = self.word_left();
+                self.buffer.delete(start, self.location);
+                self.location = start;
+                self.update_cursor_position();
+            },
+            DeleteWordForward => {
+                let end = self.word_right();
+                self.buffer.delete(self.location, end);
+                self.set_redraw(true);
+            },
             Enter => {
                 self.enter();
                 self.set_redraw(true);
             },
-            Save => {
-                self.save_file();
-            }
+            KillLine => self.kill_line(kill_ring, continues_kill),
+            KillRegion => self.kill_region(kill_ring, continues_kill),
+            CopyRegion => self.copy_region(kill_ring),
+            Yank => self.yank(kill_ring),
+            YankPop => self.yank_pop(kill_ring, last_yank),
+            SetMark => {
+                self.mark = if self.mark == Some(self.location) { None } else { Some(self.location) };
+                self.block_column = None;
+                self.set_redraw(true);
+            },
+            ToggleBlockSelection => self.toggle_block_selection(),
+            ClearMark => self.clear_mark(),
+            ToggleWrap => {
+                self.settings.wrap = self.settings.wrap.next();
+                self.top_row = 0;
+                self.update_cursor_position();
+            },
+            ToggleWhitespace => {
+                self.settings.show_whitespace = !self.settings.show_whitespace;
+                self.set_redraw(true);
+            },
+            ToggleLineNumbers => {
+                self.settings.line_numbers = self.settings.line_numbers.next();
+                self.update_cursor_position();
+            },
+            Mouse(action, position) => self.handle_mouse(action, position),
+            Scroll(delta) => self.scroll(delta),
+            ScrollLines(delta) => self.scroll_keeping_cursor(delta),
+            ScrollHalfPage(direction) => self.scroll_keeping_cursor(direction * isize::try_from((self.size.num_rows / 2).max(1)).unwrap_or(1)),
+            Recenter => self.recenter(last_recenter),
             _ => (),
         }
     }
 
-    fn move_cursor(&mut self, direction: &Direction) {
-        let Size{num_rows, ..} = self.size;        
-        match direction {
-            Direction::Up if self.location.y > 0 => {
-                self.location.y -= 1;
-                self.location.x = cmp::min(self.location.x, self.buffer.get_num_columns(self.location.y));
+    fn handle_mouse(&mut self, action: MouseAction, position: Position) {
+        let location = self.location_at(position);
+        match action {
+            MouseAction::Click => {
+                self.mark = None;
+                self.location = location;
+            },
+            MouseAction::Drag => {
+                self.mark = self.mark.or(Some(self.location));
+                self.location = location;
+            },
+            MouseAction::AddCursor => self.add_cursor(Cursor{location, ..Cursor::default()}),
+            MouseAction::DoubleClick => {
+                let word = self.buffer.get_line(location.y).map_or(0..0, |line| line.word_at(location.x));
+                self.mark = Some(Location{x: word.start, y: location.y});
+                self.location = Location{x: word.end, y: location.y};
             },
-            Direction::Down if self.location.y < self.buffer.get_num_rows() => {
-                self.location.y += 1;
-                self.location.x = cmp::min(self.location.x, self.buffer.get_num_columns(self.location.y));
+        }
+        self.update_cursor_position();
+    }
+
+    // Maps a position on screen to the buffer location drawn there, past the text meaning the end of the buffer
+    fn location_at(&self, position: Position) -> Location {
+        let tab_width = self.settings.tab_width;
+        let rows = self.visible_rows();
+        let Some((y, columns, _)) = rows.get(position.row).filter(|(y, ..)| *y < self.buffer.get_num_rows()) else {
+            let y = self.buffer.get_num_rows().saturating_sub(1);
+            return Location{x: self.buffer.get_num_columns(y), y};
+        };
+        let Some(line) = self.buffer.get_line(*y) else {
+            return Location{x: 0, y: *y};
+        };
+        let column = columns.start + position.column.saturating_sub(self.gutter_width());
+        let line_end = line.column_of(line.len(), tab_width);
+        let x = if self.settings.wrap != WrapMode::Off && column >= columns.end && columns.end < line_end {
+            // A click past the end of a wrapped row lands on its last character, not the start of the next row
+            line.index_at_column(columns.end, tab_width).saturating_sub(1)
+        }
+        else {
+            line.index_at_column(column, tab_width)
+        };
+        Location{x, y: *y}
+    }
+
+    // Moves the view without moving the cursor, which may end up off screen
+    fn scroll(&mut self, delta: isize) {
+        let last_line = self.buffer.get_num_rows().saturating_sub(1);
+        if self.settings.wrap == WrapMode::Off {
+            self.top_left.y = self.top_left.y.saturating_add_signed(delta).min(last_line);
+        }
+        else {
+            let mut top = (self.top_left.y, self.top_row);
+            for _ in 0..delta.unsigned_abs() {
+                let next = if delta > 0 { self.next_row(top).filter(|(y, _)| *y <= last_line) } else { self.previous_row(top) };
+                match next {
+                    Some(next) => top = next,
+                    None => break,
+                }
+            }
+            (self.top_left.y, self.top_row) = top;
+        }
+        self.set_redraw(true);
+    }
+
+    // Scrolls like Ctrl-E/Ctrl-Y in Vim, only moving the cursor when it would leave the view
+    fn scroll_keeping_cursor(&mut self, delta: isize) {
+        self.scroll(delta);
+        let num_rows = self.size.num_rows;
+        let margin = self.scroll_off();
+        let top = (self.top_left.y, self.top_row);
+        let cursor = self.cursor_row();
+        let above = if self.previous_row(top).is_some() { margin } else { 0 };
+        if cursor < top {
+            let distance = self.rows_between(cursor, top, usize::MAX);
+            self.move_by_rows(true, distance + above);
+        }
+        else {
+            let row = self.rows_between(top, cursor, usize::MAX);
+            let below = self.rows_below(cursor, margin);
+            if row < above {
+                self.move_by_rows(true, above - row);
+            }
+            else if row + below >= num_rows {
+                self.move_by_rows(false, row + below + 1 - num_rows.max(1));
+            }
+        }
+        self.update_cursor_position();
+    }
+
+    // Repeated presses cycle the cursor line between the middle, top and bottom of the view
+    fn recenter(&mut self, last_recenter: Option<RecenterPosition>) {
+        let num_rows = self.size.num_rows;
+        let margin = self.scroll_off();
+        let (position, rows_above) = match last_recenter {
+            None | Some(RecenterPosition::Bottom) => (RecenterPosition::Center, num_rows / 2),
+            Some(RecenterPosition::Center) => (RecenterPosition::Top, margin),
+            Some(RecenterPosition::Top) => (RecenterPosition::Bottom, num_rows.saturating_sub(margin + 1)),
+        };
+        (self.top_left.y, self.top_row) = self.rows_above(self.cursor_row(), rows_above);
+        self.last_recenter = Some(position);
+        self.update_cursor_position();
+    }
+
+    fn undo(&mut self, redo: bool) {
+        let location = if redo { self.buffer.redo() } else { self.buffer.undo() };
+        if let Some(location) = location {
+            self.location = location;
+        }
+        self.update_cursor_position();
+    }
+
+    fn jump_to_bracket(&mut self) {
+        if let Some((_, matching)) = self.bracket_pair {
+            self.location = matching;
+            self.update_cursor_position();
+        }
+    }
+
+    // The bracket under the cursor, or else the one just before it, with its match
+    fn update_bracket_pair(&mut self) {
+        let Location{x, y} = self.location;
+        self.bracket_pair = std::iter::once(x)
+            .chain(x.checked_sub(1))
+            .find_map(|x| Some((Location{x, y}, self.buffer.matching_bracket(Location{x, y})?)));
+    }
+
+    fn clear_mark(&mut self) {
+        if self.mark.take().is_some() {
+            self.set_redraw(true);
+        }
+    }
+
+    fn region(&self) -> Option<(Location, Location)> {
+        let mark = self.mark?;
+        Some((cmp::min(mark, self.location), cmp::max(mark, self.location)))
+    }
+
+    // The lines and display columns between the mark and the cursor while selecting a block
+    fn block(&self) -> Option<(Range<usize>, Range<usize>)> {
+        let (mark, mark_column) = self.mark.zip(self.block_column)?;
+        let column = self.desired_column.unwrap_or_else(|| self.cursor_column());
+        let lines = mark.y.min(self.location.y)..mark.y.max(self.location.y) + 1;
+        Some((lines, mark_column.min(column)..mark_column.max(column)))
+    }
+
+    // Turns the current selection into a block, or starts one at the cursor
+    fn toggle_block_selection(&mut self) {
+        if self.block().is_some() {
+            self.clear_mark();
+            return;
+        }
+        let mark = self.mark.unwrap_or(self.location);
+        self.block_column = Some(self.column_of(mark));
+        self.mark = Some(mark);
+        self.set_redraw(true);
+    }
+
+    fn block_text(&self, lines: Range<usize>, columns: &Range<usize>) -> String {
+        let tab_width = self.settings.tab_width;
+        lines
+            .map(|y| self.buffer.get_line(y).map_or_else(String::new, |line| line.get(line.range_at_columns(columns.clone(), tab_width))))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // Typing and deleting act on every line of the block, which then shrinks to an empty column after the edit
+    fn edit_block(&mut self, command: EditorCommand, kill_ring: &mut KillRing) {
+        let Some((lines, columns)) = self.block() else {
+            return;
+        };
+        self.kill_in_progress = false;
+        self.last_yank = None;
+        match command {
+            Insert(character) => self.replace_block(lines, &columns, &character.to_string()),
+            Backspace if columns.is_empty() => self.replace_block(lines, &(columns.start.saturating_sub(1)..columns.start), ""),
+            Delete if columns.is_empty() => self.replace_block(lines, &(columns.start..columns.start + 1), ""),
+            KillRegion | CopyRegion => {
+                kill_ring.push_block(self.block_text(lines.clone(), &columns));
+                if matches!(command, KillRegion) {
+                    self.replace_block(lines, &columns, "");
+                }
+                self.clear_mark();
             },
+            _ => self.replace_block(lines, &columns, ""),
+        }
+    }
+
+    // Lines too short to reach the block are padded with spaces before `text` is inserted
+    fn replace_block(&mut self, lines: Range<usize>, columns: &Range<usize>, text: &str) {
+        let tab_width = self.settings.tab_width;
+        let mut cursor_x = 0;
+        for y in lines {
+            let (range, line_end) = self.buffer.get_line(y).map_or((0..0, 0), |line| {
+                (line.range_at_columns(columns.clone(), tab_width), line.column_of(line.len(), tab_width))
+            });
+            if !range.is_empty() {
+                self.buffer.delete(Location{x: range.start, y}, Location{x: range.end, y});
+            }
+            let mut x = range.start;
+            if !text.is_empty() {
+                let padding = " ".repeat(columns.start.saturating_sub(line_end));
+                x = self.buffer.insert(Location{x, y}, &(padding + text)).x;
+            }
+            if y == self.location.y {
+                cursor_x = x;
+            }
+        }
+        let column = self.column_of(Location{x: cursor_x, y: self.location.y});
+        let mark_y = self.mark.map_or(self.location.y, |mark| mark.y);
+        self.mark = Some(self.location_at_column(mark_y, column));
+        self.location = self.location_at_column(self.location.y, column);
+        self.block_column = Some(column);
+        self.desired_column = Some(column);
+        self.update_cursor_position();
+    }
+
+    fn location_at_column(&self, y: usize, column: usize) -> Location {
+        let x = self.buffer.get_line(y).map_or(0, |line| line.index_at_column(column, self.settings.tab_width));
+        Location{x, y}
+    }
+
+    // Pastes each line of a block at the cursor column on successive lines
+    fn yank_block(&mut self, text: &str) {
+        let column = self.cursor_column();
+        let tab_width = self.settings.tab_width;
+        let mut end = self.location;
+        for (offset, row) in text.split('\n').enumerate() {
+            let y = self.location.y + offset;
+            let (x, line_end) = self.buffer.get_line(y).map_or((0, 0), |line| {
+                (line.index_at_column(column, tab_width), line.column_of(line.len(), tab_width))
+            });
+            let padding = " ".repeat(column.saturating_sub(line_end));
+            end = self.buffer.insert(Location{x, y}, &(padding + row));
+        }
+        self.location = end;
+        self.update_cursor_position();
+    }
+
+    fn kill(&mut self, kill_ring: &mut KillRing, start: Location, end: Location, continues_kill: bool) {
+        let text = self.buffer.delete(start, end);
+        if continues_kill {
+            kill_ring.append(&text);
+        }
+        else {
+            kill_ring.push(text);
+        }
+        self.kill_in_progress = true;
+        self.location = start;
+        self.update_cursor_position();
+    }
+
+    fn kill_line(&mut self, kill_ring: &mut KillRing, continues_kill: bool) {
+        let end_of_line = self.buffer.end_of_line(self.location);
+        let end = if self.location.x < end_of_line.x {
+            end_of_line
+        }
+        else {
+            Location{x: 0, y: self.location.y + 1}
+        };
+        self.clear_mark();
+        self.kill(kill_ring, self.location, end, continues_kill);
+    }
+
+    fn kill_region(&mut self, kill_ring: &mut KillRing, continues_kill: bool) {
+        if let Some((start, end)) = self.region() {
+            self.mark = None;
+            self.kill(kill_ring, start, end, continues_kill);
+        }
+    }
+
+    fn copy_region(&mut self, kill_ring: &mut KillRing) {
+        if let Some((start, end)) = self.region() {
+            kill_ring.push(self.buffer.get_text(start, end));
+            self.clear_mark();
+        }
+    }
+
+    fn yank(&mut self, kill_ring: &KillRing) {
+        let Some(text) = kill_ring.current().map(str::to_string) else {
+            return;
+        };
+        self.clear_mark();
+        if kill_ring.is_block() {
+            self.yank_block(&text);
+        }
+        else {
+            self.insert_yanked(&text);
+        }
+    }
+
+    fn yank_pop(&mut self, kill_ring: &mut KillRing, last_yank: Option<(Location, Location)>) {
+        let Some((start, end)) = last_yank else {
+            return;
+        };
+        let Some(text) = kill_ring.rotate().map(str::to_string) else {
+            return;
+        };
+        self.buffer.delete(start, end);
+        self.location = start;
+        self.insert_yanked(&text);
+    }
+
+    fn insert_yanked(&mut self, text: &str) {
+        let start = self.location;
+        self.location = self.buffer.insert(start, text);
+        self.last_yank = Some((start, self.location));
+        self.update_cursor_position();
+    }
+
+    fn move_cursor(&mut self, direction: Direction) {
+        let Size{num_rows, ..} = self.size;
+        let is_wrapped = self.settings.wrap != WrapMode::Off;
+        match direction {
+            Direction::Up if is_wrapped => self.move_by_rows(false, 1),
+            Direction::Down if is_wrapped => self.move_by_rows(true, 1),
+            Direction::PageUp if is_wrapped => self.move_by_rows(false, num_rows),
+            Direction::PageDown if is_wrapped => self.move_by_rows(true, num_rows),
+            Direction::Up if self.location.y > 0 => self.move_to_line(self.location.y - 1),
+            Direction::Down if self.location.y < self.buffer.get_num_rows() => self.move_to_line(self.location.y + 1),
             Direction::Left => {
                 if self.location.x > 0 {
                     self.location.x -= 1;
@@ -104,103 +775,731 @@ impl View {
                     self.location.x = 0;
                 }
             },
-            Direction::PageUp => {
-                self.location.y = cmp::max(0, self.location.y - num_rows);
-                self.location.x = cmp::min(self.location.x, self.buffer.get_num_columns(self.location.y));
-            },
-            Direction::PageDown => {
-                self.location.y = cmp::min(self.buffer.get_num_rows(), self.location.y + num_rows);
-                self.location.x = cmp::min(self.location.x, self.buffer.get_num_columns(self.location.y));
-            },
+            Direction::PageUp => self.move_to_line(self.location.y.saturating_sub(num_rows)),
+            Direction::PageDown => self.move_to_line(cmp::min(self.buffer.get_num_rows(), self.location.y + num_rows)),
             Direction::Home => {
                 self.location.x = 0;
             },
             Direction::End => {
                 self.location.x = self.buffer.get_num_columns(self.location.y);
             },
+            Direction::WordLeft => {
+                self.location = self.word_left();
+            },
+            Direction::WordRight => {
+                self.location = self.word_right();
+            },
+            Direction::BufferStart => {
+                self.location = Location::default();
+            },
+            Direction::BufferEnd => {
+                let y = self.buffer.get_num_rows().saturating_sub(1);
+                self.location = Location{x: self.buffer.get_num_columns(y), y};
+            },
             _ => (),
         }
         self.update_cursor_position();
     }
 
+    // Vertical movement aims for the column the cursor had before passing through shorter lines
+    fn goal_column(&mut self, column: usize) -> usize {
+        *self.desired_column.get_or_insert(column)
+    }
+
+    fn move_to_line(&mut self, y: usize) {
+        let column = self.goal_column(self.cursor_column());
+        let x = self.buffer.get_line(y).map_or(0, |line| line.index_at_column(column, self.settings.tab_width));
+        self.location = Location{x, y};
+    }
+
+    fn line_rows(&self, y: usize) -> Vec<Range<usize>> {
+        match (self.settings.wrap, self.buffer.get_line(y)) {
+            (WrapMode::Off, _) | (_, None) => std::iter::once(0..self.buffer.get_num_columns(y)).collect(),
+            (wrap, Some(line)) => line.wrap(self.text_width(), wrap == WrapMode::Word, self.settings.tab_width),
+        }
+    }
+
+    fn row_index(rows: &[Range<usize>], x: usize) -> usize {
+        rows.iter().rposition(|row| row.start <= x).unwrap_or(0)
+    }
+
+    fn column_of(&self, location: Location) -> usize {
+        self.buffer.get_line(location.y).map_or(0, |line| line.column_of(location.x, self.settings.tab_width))
+    }
+
+    fn cursor_column(&self) -> usize {
+        self.column_of(self.location)
+    }
+
+    fn cursor_row(&self) -> (usize, usize) {
+        (self.location.y, Self::row_index(&self.line_rows(self.location.y), self.location.x))
+    }
+
+    // Visual rows are addressed as (line index, row within that line)
+    fn next_row(&self, (y, row): (usize, usize)) -> Option<(usize, usize)> {
+        if row + 1 < self.line_rows(y).len() {
+            Some((y, row + 1))
+        }
+        else if y < self.buffer.get_num_rows() {
+            Some((y + 1, 0))
+        }
+        else {
+            None
+        }
+    }
+
+    fn previous_row(&self, (y, row): (usize, usize)) -> Option<(usize, usize)> {
+        if row > 0 {
+            Some((y, row - 1))
+        }
+        else if y > 0 {
+            Some((y - 1, self.line_rows(y - 1).len() - 1))
+        }
+        else {
+            None
+        }
+    }
+
+    fn rows_above(&self, mut position: (usize, usize), count: usize) -> (usize, usize) {
+        for _ in 0..count {
+            match self.previous_row(position) {
+                Some(previous) => position = previous,
+                None => break,
+            }
+        }
+        position
+    }
+
+    // Counts the rows of text after `position`, up to `count`
+    fn rows_below(&self, mut position: (usize, usize), count: usize) -> usize {
+        let mut found = 0;
+        while found < count {
+            match self.next_row(position).filter(|(y, _)| *y < self.buffer.get_num_rows()) {
+                Some(next) => position = next,
+                None => break,
+            }
+            found += 1;
+        }
+        found
+    }
+
+    fn rows_between(&self, from: (usize, usize), to: (usize, usize), limit: usize) -> usize {
+        let mut position = from;
+        let mut distance = 0;
+        while position < to && distance < limit {
+            match self.next_row(position) {
+                Some(next) => position = next,
+                None => break,
+            }
+            distance += 1;
+        }
+        distance
+    }
+
+    fn move_by_rows(&mut self, down: bool, count: usize) {
+        let rows = self.line_rows(self.location.y);
+        let row = Self::row_index(&rows, self.location.x);
+        let column = self.goal_column(self.cursor_column() - self.column_of(Location{x: rows[row].start, y: self.location.y}));
+        let mut position = (self.location.y, row);
+        for _ in 0..count {
+            let next = if down { self.next_row(position) } else { self.previous_row(position) };
+            match next {
+                Some(next) => position = next,
+                None => break,
+            }
+        }
+        let rows = self.line_rows(position.0);
+        let range = &rows[position.1];
+        let max_x = if position.1 + 1 < rows.len() { range.end.saturating_sub(1).max(range.start) } else { range.end };
+        let x = self.buffer.get_line(position.0).map_or(0, |line| {
+            let row_column = line.column_of(range.start, self.settings.tab_width);
+            line.index_at_column(row_column + column, self.settings.tab_width)
+        });
+        self.location = Location{x: x.clamp(range.start, max_x), y: position.0};
+    }
+
+    fn word_left(&self) -> Location {
+        let Location{x, y} = self.location;
+        match self.buffer.get_line(y) {
+            Some(line) if x > 0 => Location{x: line.previous_word_start(x), y},
+            _ if y > 0 => Location{x: self.buffer.get_num_columns(y - 1), y: y - 1},
+            _ => self.location,
+        }
+    }
+
+    fn word_right(&self) -> Location {
+        let Location{x, y} = self.location;
+        match self.buffer.get_line(y) {
+            Some(line) if x < line.len() => Location{x: line.next_word_end(x), y},
+            Some(_) if y + 1 < self.buffer.get_num_rows() => Location{x: 0, y: y + 1},
+            _ => self.location,
+        }
+    }
+
+    pub fn goto(&mut self, target: GotoTarget) {
+        let Location{x, y} = target.resolve(self.location, self.buffer.get_num_rows());
+        self.desired_column = None;
+        self.location = Location{x: x.min(self.buffer.get_num_columns(y)), y};
+        self.center_on_cursor();
+        self.update_bracket_pair();
+    }
+
+    fn gutter_width(&self) -> usize {
+        if self.settings.line_numbers == LineNumbers::Off || self.buffer.is_empty() {
+            0
+        }
+        else {
+            self.buffer.get_num_rows().to_string().len() + 1
+        }
+    }
+
+    fn text_width(&self) -> usize {
+        self.size.num_columns.saturating_sub(self.gutter_width()).max(1)
+    }
+
+    fn center_on_cursor(&mut self) {
+        let num_rows = self.size.num_rows;
+        let num_columns = self.text_width();
+        if self.settings.wrap != WrapMode::Off {
+            (self.top_left.y, self.top_row) = self.rows_above(self.cursor_row(), num_rows / 2);
+            self.update_cursor_position();
+            return;
+        }
+        let column = self.cursor_column();
+        self.top_left.y = self.location.y.saturating_sub(num_rows / 2);
+        self.top_left.x = if column < num_columns {
+            0
+        }
+        else {
+            column - num_columns / 2
+        };
+        self.update_cursor_position();
+    }
+
+    // Never more than half the view, so there is always a row the cursor can sit on
+    fn scroll_off(&self) -> usize {
+        self.settings.scroll_off.min(self.size.num_rows.saturating_sub(1) / 2)
+    }
+
     fn update_cursor_position(&mut self) {
-        let Size{num_rows, num_columns} = self.size;
-        if self.location.x < self.top_left.x {
-            self.top_left.x = self.location.x;
+        let num_rows = self.size.num_rows;
+        let num_columns = self.text_width();
+        if self.settings.wrap != WrapMode::Off {
+            self.update_wrapped_scroll();
+            return;
+        }
+        self.top_row = 0;
+        let column = self.cursor_column();
+        if column < self.top_left.x {
+            self.top_left.x = column;
         }
-        if self.location.y < self.top_left.y {
-            self.top_left.y = self.location.y;
+        let margin = self.scroll_off();
+        let below = self.rows_below(self.cursor_row(), margin);
+        if self.location.y < self.top_left.y + margin {
+            self.top_left.y = self.location.y.saturating_sub(margin);
         }
-        if self.location.x >= self.top_left.x + num_columns {
-            self.top_left.x = self.location.x - num_columns + 1;
+        if column >= self.top_left.x + num_columns {
+            self.top_left.x = column - num_columns + 1;
+        }
+        if self.location.y + below >= self.top_left.y + num_rows {
+            self.top_left.y = self.location.y + below + 1 - num_rows;
+        }
+        self.set_redraw(true);
+    }
+
+    fn update_wrapped_scroll(&mut self) {
+        let num_rows = self.size.num_rows.max(1);
+        let margin = self.scroll_off();
+        let cursor = self.cursor_row();
+        let top = (self.top_left.y, self.top_row);
+        let above = self.rows_above(cursor, margin);
+        let below = self.rows_below(cursor, margin);
+        if above < top {
+            (self.top_left.y, self.top_row) = above;
         }
-        if self.location.y >= self.top_left.y + num_rows {
-            self.top_left.y = self.location.y - num_rows + 1;
+        else if self.rows_between(top, cursor, num_rows) + below >= num_rows {
+            (self.top_left.y, self.top_row) = self.rows_above(cursor, num_rows - 1 - below);
         }
+        self.top_left.x = 0;
         self.set_redraw(true);
     }
 
-    pub fn get_cursor_position(&self) -> Position {
-        Position{row: self.location.y - self.top_left.y, column: self.location.x - self.top_left.x}
+    // `None` when scrolling has moved the cursor off screen
+    pub fn get_cursor_position(&self) -> Option<Position> {
+        self.screen_position(self.location)
+    }
+
+    fn screen_position(&self, location: Location) -> Option<Position> {
+        let num_rows = self.size.num_rows;
+        if self.settings.wrap != WrapMode::Off {
+            let rows = self.line_rows(location.y);
+            let row = Self::row_index(&rows, location.x);
+            let top = (self.top_left.y, self.top_row);
+            let screen_row = self.rows_between(top, (location.y, row), num_rows);
+            if (location.y, row) < top || screen_row >= num_rows {
+                return None;
+            }
+            return Some(Position{
+                row: screen_row,
+                column: self.gutter_width() + self.column_of(location) - self.column_of(Location{x: rows[row].start, y: location.y}),
+            });
+        }
+        let column = self.column_of(location);
+        if !(self.top_left.y..self.top_left.y + num_rows).contains(&location.y) || column < self.top_left.x {
+            return None;
+        }
+        Some(Position{row: location.y - self.top_left.y, column: self.gutter_width() + column - self.top_left.x})
+    }
+
+    // Lists the (line index, display columns, is first row of line) shown on each screen row
+    fn visible_rows(&self) -> Vec<(usize, Range<usize>, bool)> {
+        let num_rows = self.size.num_rows;
+        let Location{x, y} = self.top_left;
+        if self.settings.wrap == WrapMode::Off {
+            let end = x + self.text_width();
+            return (y..y + num_rows).map(|line_index| (line_index, x..end, true)).collect();
+        }
+        let mut rows = Vec::with_capacity(num_rows);
+        let mut position = Some((y, self.top_row));
+        while let Some((line_index, row)) = position {
+            if rows.len() >= num_rows || line_index >= self.buffer.get_num_rows() {
+                break;
+            }
+            let range = &self.line_rows(line_index)[row];
+            let columns = self.column_of(Location{x: range.start, y: line_index})..self.column_of(Location{x: range.end, y: line_index});
+            rows.push((line_index, columns, row == 0));
+            position = self.next_row((line_index, row));
+        }
+        rows
     }
 
     fn add_character(&mut self, character: char) {
+        let (_, next) = self.neighbours();
+        if let Some(index) = self.auto_closer_at(self.location).filter(|_| next.starts_with(character)) {
+            self.auto_closers.remove(index);
+            self.move_cursor(Direction::Right);
+            return;
+        }
+        let closer = self.settings.auto_pairs.then(|| self.auto_pair(character)).flatten();
+        let is_blank = self.buffer.get_line(self.location.y).is_some_and(|line| !line.string.is_empty() && line.string.trim().is_empty());
+        if is_blank && self.file_type().block_closers().contains(&character) {
+            self.dedent_lines(self.location.y..self.location.y + 1);
+        }
         self.buffer.insert_character(self.location, character);
-        self.move_cursor(&Direction::Right);
+        self.move_cursor(Direction::Right);
+        if let Some(closer) = closer {
+            self.buffer.insert_character(self.location, closer);
+            self.auto_closers.push((self.location, self.buffer.edit_count()));
+        }
+    }
+
+    // Brings the closers up to date with the edits made since each was placed, which only stays valid within one change
+    fn shift_auto_closers(&mut self) {
+        let edit_count = self.buffer.edit_count();
+        for (closer, since) in &mut self.auto_closers {
+            *closer = self.buffer.edits_since(*since).iter().fold(*closer, |closer, edit| edit.shift(closer));
+            *since = edit_count;
+        }
+    }
+
+    fn auto_closer_at(&self, location: Location) -> Option<usize> {
+        self.auto_closers
+            .iter()
+            .position(|(closer, since)| self.buffer.edits_since(*since).iter().fold(*closer, |closer, edit| edit.shift(closer)) == location)
+    }
+
+    // The graphemes either side of the cursor, empty at the ends of the line
+    fn neighbours(&self) -> (String, String) {
+        let Location{x, y} = self.location;
+        self.buffer.get_line(y).map_or_else(Default::default, |line| (line.get(x.saturating_sub(1)..x), line.get(x..x + 1)))
+    }
+
+    // Only pairs in front of whitespace or a closer, and quotes not after a letter, so apostrophes stay single
+    fn auto_pair(&self, character: char) -> Option<char> {
+        let (open, close) = *PAIRS.iter().find(|(open, _)| *open == character)?;
+        let (previous, next) = self.neighbours();
+        let is_word = previous.chars().any(|character| character.is_alphanumeric() || character == '_');
+        let next_is_free = next.chars().all(char::is_whitespace) || PAIRS.iter().any(|(_, close)| next.starts_with(*close));
+        (next_is_free && !(open == close && is_word)).then_some(close)
+    }
+
+    fn delete_pair(&mut self) {
+        if let Some(index) = self.auto_closer_at(self.location) {
+            self.auto_closers.remove(index);
+        }
+        let Location{x, y} = self.location;
+        self.buffer.delete(Location{x: x - 1, y}, Location{x: x + 1, y});
+        self.location.x -= 1;
+        self.update_cursor_position();
+    }
+
+    fn is_inside_empty_pair(&self) -> bool {
+        if self.auto_closer_at(self.location).is_none() {
+            return false;
+        }
+        let (previous, next) = self.neighbours();
+        PAIRS.iter().any(|(open, close)| previous.starts_with(*open) && previous.len() == open.len_utf8() && next.starts_with(*close))
+    }
+
+    // Moves the cursor and mark along with lines that have moved
+    fn shift_lines(&mut self, delta: isize) {
+        self.location.y = self.location.y.saturating_add_signed(delta);
+        if let Some(mark) = &mut self.mark {
+            mark.y = mark.y.saturating_add_signed(delta);
+        }
+        self.update_cursor_position();
+    }
+
+    // The copy goes below, taking the cursor and selection with it
+    fn duplicate_lines(&mut self) {
+        let lines = self.selected_lines();
+        if lines.is_empty() {
+            return;
+        }
+        let text = self.buffer.get_lines(lines.clone());
+        self.buffer.insert_lines(lines.end, &text);
+        self.shift_lines(isize::try_from(lines.len()).unwrap_or(0));
+    }
+
+    // Swaps the selected lines with the one above or below them
+    fn move_lines(&mut self, down: bool) {
+        let lines = self.selected_lines();
+        if lines.is_empty() || !down && lines.start == 0 || down && lines.end >= self.buffer.get_num_rows() {
+            return;
+        }
+        if down {
+            let text = self.buffer.delete_lines(lines.end..lines.end + 1);
+            self.buffer.insert_lines(lines.start, &text);
+        }
+        else {
+            let text = self.buffer.delete_lines(lines.start - 1..lines.start);
+            self.buffer.insert_lines(lines.end - 1, &text);
+        }
+        self.shift_lines(if down { 1 } else { -1 });
+    }
+
+    fn clamp_to_line(&self, location: Location) -> Location {
+        Location{x: location.x.min(self.buffer.get_num_columns(location.y)), y: location.y}
+    }
+
+    fn delete_lines(&mut self) {
+        let lines = self.selected_lines();
+        self.buffer.delete_lines(lines.clone());
+        self.mark = None;
+        self.location = self.clamp_to_line(Location{x: self.location.x, y: lines.start.min(self.buffer.get_num_rows().saturating_sub(1))});
+        self.update_cursor_position();
+    }
+
+    // Joins the selected lines, or the cursor line with the next, leaving single spaces between them
+    fn join_lines(&mut self) {
+        let lines = self.selected_lines();
+        let y = lines.start;
+        for _ in 0..lines.len().saturating_sub(1).max(1) {
+            let (Some(line), Some(next)) = (self.buffer.get_line(y), self.buffer.get_line(y + 1)) else {
+                break;
+            };
+            let trailing = line.string.chars().rev().take_while(|character| character.is_whitespace()).count();
+            let leading = next.string.chars().take_while(|character| character.is_whitespace()).count();
+            let separator = if line.string.trim().is_empty() || next.string.trim().is_empty() { "" } else { " " };
+            let start = Location{x: line.len() - trailing, y};
+            self.buffer.delete(start, Location{x: leading, y: y + 1});
+            self.buffer.insert(start, separator);
+            self.location = start;
+        }
+        self.mark = None;
+        self.update_cursor_position();
+    }
+
+    fn sort_lines(&mut self) {
+        let lines = self.selected_lines();
+        if lines.len() < 2 {
+            return;
+        }
+        let start = Location{x: 0, y: lines.start};
+        let end = self.buffer.end_of_line(Location{x: 0, y: lines.end - 1});
+        let text = self.buffer.get_text(start, end);
+        let mut sorted: Vec<&str> = text.split('\n').collect();
+        sorted.sort_unstable();
+        let sorted = sorted.join("\n");
+        if sorted != text {
+            self.buffer.delete(start, end);
+            self.buffer.insert(start, &sorted);
+            self.location = self.clamp_to_line(self.location);
+            self.mark = self.mark.map(|mark| self.clamp_to_line(mark));
+            self.update_cursor_position();
+        }
+    }
+
+    fn dedent_width(&self, y: usize) -> usize {
+        let Some(line) = self.buffer.get_line(y) else {
+            return 0;
+        };
+        let tab_width = self.settings.tab_width;
+        let leading_spaces = line.string.chars().take_while(|character| *character == ' ').count();
+        if line.string.starts_with('\t') {
+            1
+        }
+        else if leading_spaces % tab_width == 0 {
+            leading_spaces.min(tab_width)
+        }
+        else {
+            leading_spaces % tab_width
+        }
+    }
+
+    // Lines touched by the selection, or just the cursor line without one
+    fn selected_lines(&self) -> Range<usize> {
+        let (start, end) = self.region().unwrap_or((self.location, self.location));
+        let last = if end.y > start.y && end.x == 0 { end.y } else { end.y + 1 };
+        start.y..last.min(self.buffer.get_num_rows())
+    }
+
+    // Replaces part of a line while keeping the cursor and mark on the same text
+    fn replace_in_line(&mut self, y: usize, columns: Range<usize>, text: &str) {
+        self.buffer.delete(Location{x: columns.start, y}, Location{x: columns.end, y});
+        if !text.is_empty() {
+            self.buffer.insert(Location{x: columns.start, y}, text);
+        }
+        let inserted = Line::from(text).len();
+        self.location = shift_location(self.location, y, &columns, inserted);
+        self.mark = self.mark.map(|mark| shift_location(mark, y, &columns, inserted));
+    }
+
+    fn indent_lines(&mut self, lines: Range<usize>) {
+        let unit = self.indent_unit();
+        for y in lines {
+            if self.buffer.get_num_columns(y) > 0 {
+                self.replace_in_line(y, 0..0, &unit);
+            }
+        }
+        self.update_cursor_position();
+    }
+
+    fn dedent_lines(&mut self, lines: Range<usize>) {
+        for y in lines {
+            let width = self.dedent_width(y);
+            self.replace_in_line(y, 0..width, "");
+        }
+        self.update_cursor_position();
+    }
+
+    // Comments out the lines unless every non-blank one is already commented, lining the tokens up at the smallest indent
+    fn toggle_comment(&mut self) {
+        let Some(token) = self.file_type().line_comment() else {
+            return;
+        };
+        // Each non-blank line with its indent and, when commented, the width of the token and a following space
+        let lines: Vec<(usize, usize, Option<usize>)> = self.selected_lines()
+            .filter_map(|y| {
+                let line = self.buffer.get_line(y)?;
+                let text = line.string.trim_start();
+                let indent = line.string.chars().count() - text.chars().count();
+                let comment = text.strip_prefix(token).map(|rest| token.len() + usize::from(rest.starts_with(' ')));
+                (!text.is_empty()).then_some((y, indent, comment))
+            })
+            .collect();
+        if !lines.is_empty() && lines.iter().all(|(_, _, comment)| comment.is_some()) {
+            for (y, indent, comment) in lines {
+                self.replace_in_line(y, indent..indent + comment.unwrap_or(0), "");
+            }
+        }
+        else {
+            let column = lines.iter().map(|(_, indent, _)| *indent).min().unwrap_or(0);
+            for (y, _, _) in lines {
+                self.replace_in_line(y, column..column, &format!("{token} "));
+            }
+        }
+        self.update_cursor_position();
     }
 
     fn delete_character(&mut self){
         self.buffer.delete_character(self.location);
     }
 
+    // Carries the indentation over to the new line and drops trailing whitespace from the one being left
     fn enter(&mut self) {
-        self.buffer.enter(self.location);
-        self.move_cursor(&Direction::Down);
-        self.move_cursor(&Direction::Home);
+        let Location{x, y} = self.location;
+        let Some(line) = self.buffer.get_line(y) else {
+            self.buffer.enter(self.location);
+            self.move_cursor(Direction::Down);
+            self.move_cursor(Direction::Home);
+            return;
+        };
+        let before = line.get(0..x);
+        let base: String = before.chars().take_while(|character| *character == ' ' || *character == '\t').collect();
+        let opens_block = before.trim_end().ends_with(self.file_type().block_openers());
+        // Between a bracket pair the closer moves down to its own line, leaving an indented one for the cursor
+        let closes_block = opens_block && line.get(x..line.len()).starts_with(self.file_type().block_closers());
+        let indent = if opens_block { base.clone() + &self.indent_unit() } else { base.clone() };
+        let trailing = before.chars().rev().take_while(|character| character.is_whitespace()).count();
+        let start = Location{x: x - trailing, y};
+        self.buffer.delete(start, self.location);
+        self.location = self.buffer.insert(start, &format!("\n{indent}"));
+        if closes_block {
+            self.buffer.insert(self.location, &format!("\n{base}"));
+        }
+        self.update_cursor_position();
     }
 
-    fn save_file(&mut self) {
-        let _ = self.buffer.save_file();
+    fn indent_unit(&self) -> String {
+        if self.settings.soft_tabs {
+            " ".repeat(self.settings.tab_width)
+        }
+        else {
+            "\t".to_string()
+        }
     }
 
-    fn render_lines(&self, start_row: usize) -> Result<(), Error> {
-        let Size{num_rows, num_columns} = self.size;
-        let Location{x, y} = self.top_left;
+    pub fn save_file(&mut self, file_path: Option<&str>) -> Result<(), Error> {
+        // A new path is only kept once saving to it has worked
+        match file_path {
+            Some(file_path) => {
+                self.buffer.save_as(file_path)?;
+                self.buffer.save_file_path = Some(file_path.to_string());
+            },
+            None => self.buffer.save_file()?,
+        }
+        self.buffer.mark_saved();
+        self.set_redraw(true);
+        Ok(())
+    }
+
+    pub fn has_file_path(&self) -> bool {
+        self.buffer.save_file_path.is_some()
+    }
+
+    fn render_gutter(&self, screen: &mut Screen, theme: &Theme, line_index: usize, is_first_row: bool) {
+        let width = self.gutter_width();
+        if width == 0 {
+            return;
+        }
+        if line_index >= self.buffer.get_num_rows() || !is_first_row {
+            screen.set_style(theme.style(Face::Gutter));
+            screen.print(&" ".repeat(width));
+            return;
+        }
+        let distance = line_index.abs_diff(self.location.y);
+        let number = match self.settings.line_numbers {
+            LineNumbers::Relative => distance,
+            LineNumbers::Hybrid if distance > 0 => distance,
+            _ => line_index + 1,
+        };
+        let face = if distance == 0 { Face::GutterCurrent } else { Face::Gutter };
+        screen.set_style(theme.style(Face::Gutter).patch(theme.style(face)));
+        screen.print(&format!("{number:>digits$} ", digits = width - 1));
+    }
+
+    fn render_lines(&self, screen: &mut Screen, theme: &Theme, start_row: usize) {
+        let num_rows = self.size.num_rows;
+        let rows = self.visible_rows();
 
         for row in start_row..num_rows {
-            Terminal::move_cursor_to(Position{row, column: 0})?;
-            Terminal::clear_line()?;
-            if let Some(line) = self.buffer.get_line(row + y) {
-                Terminal::print(&line.get(x..x+num_columns))?;
+            screen.move_cursor_to(Position{row, column: 0});
+            screen.set_style(theme.style(Face::Text));
+            screen.clear_line();
+            match rows.get(row) {
+                Some((line_index, range, is_first_row)) => {
+                    self.render_gutter(screen, theme, *line_index, *is_first_row);
+                    self.render_row(screen, theme, *line_index, range.clone());
+                },
+                None => self.render_gutter(screen, theme, self.buffer.get_num_rows(), false),
             }
         }
-        Ok(())
     }
 
-    fn render_welcome_message(&self, start_row: usize) -> Result<(), Error> {
+    fn render_row(&self, screen: &mut Screen, theme: &Theme, line_index: usize, columns: Range<usize>) {
+        let Some(line) = self.buffer.get_line(line_index) else {
+            return;
+        };
+        let Range{start, end} = columns;
+        let text = theme.style(Face::Text);
+        let selection = match (self.block(), self.region()) {
+            (Some((lines, columns)), _) => lines.contains(&line_index).then_some(columns),
+            (None, Some((region_start, region_end))) if (region_start.y..=region_end.y).contains(&line_index) => {
+                let from = if region_start.y == line_index { self.column_of(region_start) } else { start };
+                let to = if region_end.y == line_index { self.column_of(region_end) } else { end };
+                Some(from..to)
+            },
+            _ => None,
+        };
+        match selection {
+            Some(selected) => {
+                let (from, to) = (selected.start.clamp(start, end), selected.end.clamp(start, end));
+                self.render_glyphs(screen, theme, text, line, start..from);
+                self.render_glyphs(screen, theme, text.patch(theme.style(Face::Selection)), line, from..to);
+                self.render_glyphs(screen, theme, text, line, to..end);
+            },
+            _ => self.render_glyphs(screen, theme, text, line, start..end),
+        }
+        let line_end = line.column_of(line.len(), self.settings.tab_width);
+        if self.settings.show_whitespace && line_end >= start && line_end < start + self.text_width() {
+            screen.set_style(text.patch(theme.style(Face::Whitespace)));
+            screen.print("¬");
+        }
+    }
+
+    fn render_glyphs(&self, screen: &mut Screen, theme: &Theme, style: Style, line: &Line, columns: Range<usize>) {
+        let tab_width = self.settings.tab_width;
+        let show_whitespace = self.settings.show_whitespace;
+        let trailing_column = line.trailing_whitespace_column(tab_width);
+        let marker_style = style.patch(theme.style(Face::Whitespace));
+        for glyph in line.render(columns, tab_width) {
+            let marker = match glyph.kind {
+                GlyphKind::Control => Some(glyph.text.clone()),
+                GlyphKind::Tab if show_whitespace => Some(format!("→{}", " ".repeat(glyph.width.saturating_sub(1)))),
+                GlyphKind::Space if show_whitespace && glyph.column >= trailing_column => Some("·".to_string()),
+                GlyphKind::NonBreakingSpace if show_whitespace => Some("⍽".to_string()),
+                _ => None,
+            };
+            screen.set_style(if marker.is_some() { marker_style } else { style });
+            screen.print(marker.as_deref().unwrap_or(&glyph.text));
+        }
+    }
+
+    // Restyles the visible part of a range that has already been drawn, one screen row of `rows` at a time
+    fn highlight_range(&self, screen: &mut Screen, rows: &[(usize, Range<usize>, bool)], (start, end): (Location, Location), style: Style) {
+        let gutter_width = self.gutter_width();
+        for (row, (y, columns, _)) in rows.iter().enumerate().filter(|(_, (y, _, _))| (start.y..=end.y).contains(y)) {
+            let from = if *y == start.y { self.column_of(start) } else { 0 };
+            let to = self.column_of(if *y == end.y { end } else { self.buffer.end_of_line(Location{x: 0, y: *y}) });
+            for column in from.max(columns.start)..to.min(columns.end) {
+                screen.patch_style(Position{row, column: gutter_width + column - columns.start}, style);
+            }
+        }
+    }
+
+    fn render_welcome_message(&self, screen: &mut Screen, theme: &Theme, start_row: usize) {
         let Size{num_rows, ..} = self.size;
+        screen.set_style(theme.style(Face::Text));
         for row in start_row..num_rows {
-            Terminal::move_cursor_to(Position{row, column: 0})?;
-            Terminal::clear_line()?;
-            Terminal::print("~")?;
+            screen.move_cursor_to(Position{row, column: 0});
+            screen.clear_line();
+            screen.print("~");
         }
-        self.draw_welcome_message()?;
-        Ok(())
+        self.draw_welcome_message(screen);
     }
 
-    fn draw_welcome_message(&self) -> Result<(), Error> {
+    fn draw_welcome_message(&self, screen: &mut Screen) {
         let Size{num_rows, num_columns} = self.size;
         let row = num_rows / 3;
         let mut message = format!("{NAME} Editor -- v{VERSION}");
         message.truncate(num_columns - 1);
         let column = (num_columns - message.len()) / 2;
-        Terminal::move_cursor_to(Position{row, column})?;
-        Terminal::print(&message)?;
-        Ok(())
+        screen.move_cursor_to(Position{row, column});
+        screen.print(&message);
     }
 
 }
 
+fn shift_location(location: Location, y: usize, columns: &Range<usize>, inserted: usize) -> Location {
+    if location.y != y || location.x <= columns.start {
+        location
+    }
+    else {
+        Location{x: location.x.max(columns.end) - columns.len() + inserted, y}
+    }
+}
+
 impl UIComponent for View {
     
     fn set_redraw(&mut self, needs_redraw: bool) {
@@ -215,15 +1514,31 @@ impl UIComponent for View {
         self.size = size;
     }
 
-    // TODO: Check for invisible characters and render them appropriately
-    fn draw(&mut self, start_row: usize) -> Result<(), Error> {
+    fn draw(&mut self, screen: &mut Screen, theme: &Theme, start_row: usize) {
         if self.buffer.is_empty() {
-            self.render_welcome_message(start_row)?;
+            self.render_welcome_message(screen, theme, start_row);
         }
         else {
-            self.render_lines(start_row)?;
+            self.render_lines(screen, theme, start_row);
+            if let Some(position) = self.bracket_pair.and_then(|(_, matching)| self.screen_position(matching)) {
+                screen.patch_style(position, theme.style(Face::MatchingBracket));
+            }
+            // An empty block shows where typing will go on each of its lines
+            if let Some((lines, columns)) = self.block().filter(|(_, columns)| columns.is_empty()) {
+                for y in lines.filter(|&y| y != self.location.y) {
+                    if let Some(position) = self.screen_position(self.location_at_column(y, columns.start)) {
+                        screen.patch_style(position, theme.style(Face::SecondaryCursor));
+                    }
+                }
+            }
+            let rows = if self.cursors.iter().any(|cursor| cursor.mark.is_some()) { self.visible_rows() } else { Vec::new() };
+            for cursor in &self.cursors {
+                self.highlight_range(screen, &rows, cursor.range(), theme.style(Face::Selection));
+                if let Some(position) = self.screen_position(cursor.location) {
+                    screen.patch_style(position, theme.style(Face::SecondaryCursor));
+                }
+            }
         }
-        Ok(())
     }
 
 }
\ No newline at end of file